@@ -1,334 +1,4820 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
+use async_trait::async_trait;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
 use log::{debug, trace};
 use reqwest::header::HeaderMap;
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
 use url::Url;
+#[cfg(target_arch = "wasm32")]
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
 
 #[cfg(test)]
 mod test;
 
-#[derive(Debug)]
+/// The default for [GoogleReaderBuilder::max_response_bytes], 64MiB
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The default for [GoogleReaderBuilder::max_item_count]
+const DEFAULT_MAX_ITEM_COUNT: usize = 100_000;
+
+/// The default for [GoogleReaderBuilder::max_retry_after].
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The most item IDs we send in one `edit-tag` request, bigger batches are split up to keep the form a sensible size.
+pub const EDIT_TAG_BATCH_SIZE: usize = 250;
+
+#[derive(Clone)]
 /// A Google Reader client
 ///
-/// This should be instantiated with `GoogleReader::try_new()`, as a `mut` variable because login sets the authtoken.
+/// This should be instantiated with `GoogleReader::try_new()` or `GoogleReader::builder()`.
+///
+/// The auth and write tokens are kept behind a shared lock rather than in the handle, and clones share them
+/// along with the HTTP client, so a reader can be cloned into parallel tasks and they'll only log in once
+/// between them. Requests still take `&mut self` for each handle's own state, like the last response headers
+/// and sync stats.
 pub struct GoogleReader {
     username: String,
-    password: String,
+    /// Shared by clones so [GoogleReader::logout] wipes it for all of them, `None` once logged out
+    password: Arc<Mutex<Option<String>>>,
     /// The server URL, e.g. `https://example.com/api/greader.php` for FreshRSS
     server_url: Url,
-    authtoken: Option<String>,
-    write_token: Option<String>,
+    /// Path segments between the server URL and the API endpoints, `reader/api/0` unless it's been changed
+    api_path: Vec<String>,
+    auth_mode: AuthMode,
+    backend: Backend,
+    timestamp_unit: TimestampUnit,
+    authtoken: Arc<Mutex<Option<String>>>,
+    write_token: Arc<Mutex<Option<String>>>,
+    /// Held while a login request is in flight, so concurrent callers don't all log in, with the result of
+    /// the last login for anyone who was waiting on it.
+    login_lock: Arc<tokio::sync::Mutex<Option<LoginResult>>>,
+    client: Client,
+    /// Headers from the most recent response, for rate-limit introspection
+    last_response_headers: Option<HeaderMap>,
+    max_response_bytes: usize,
+    max_item_count: usize,
+    /// How many times we'll wait out a `429 Too Many Requests` and try again
+    rate_limit_retries: usize,
+    /// The longest `Retry-After` we'll wait out
+    max_retry_after: Duration,
+    login_timeout: Option<Duration>,
+    login_method: LoginMethod,
+    write_token_in_query: bool,
+    auto_login: bool,
+    default_subscription_folder: Option<String>,
+    include_all_direct_stream_ids: bool,
+    collect_stats: bool,
+    stats: SyncStats,
+    /// When [GoogleReader::start_sync] was called
+    sync_started: Option<Instant>,
+    /// Shared by clones, so they're limited together
+    rate_limiter: Option<Arc<Mutex<RateLimiter>>>,
+}
+
+#[derive(Debug)]
+/// A token bucket, see [GoogleReaderBuilder::rate_limit]
+struct RateLimiter {
+    requests_per_second: f64,
+    /// Up to a second's worth of requests can be sent in a burst
+    capacity: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: f64) -> Self {
+        let capacity = requests_per_second.max(1.0);
+        RateLimiter {
+            requests_per_second,
+            capacity,
+            tokens: capacity,
+            refilled: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.requests_per_second).min(self.capacity);
+        self.refilled = now;
+    }
+
+    /// Take a token if there is one, otherwise how long until there will be.
+    fn try_acquire(&mut self) -> Result<(), Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.requests_per_second,
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Counters for the requests a [GoogleReader] makes, if it's built with [GoogleReaderBuilder::collect_stats]
+///
+/// Each clone of a reader keeps its own.
+pub struct SyncStats {
+    /// Requests sent, including retries
+    pub requests_made: usize,
+    /// API response body bytes read, after decompression
+    pub bytes_received: u64,
+    /// How long it was from [GoogleReader::start_sync] to [GoogleReader::finish_sync] last time
+    pub last_sync_duration: Option<Duration>,
+}
+
+/// Stands in for a secret in `Debug` output, so logging a client doesn't leak credentials.
+const REDACTED: &str = "***";
+
+/// Redacts a secret that might not be set, so you can still see whether it is.
+fn redact_option<T>(secret: &Option<T>) -> Option<&'static str> {
+    secret.as_ref().map(|_| REDACTED)
+}
+
+impl fmt::Debug for GoogleReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GoogleReader")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("server_url", &self.server_url)
+            .field("api_path", &self.api_path)
+            .field("auth_mode", &self.auth_mode)
+            .field("backend", &self.backend)
+            .field("timestamp_unit", &self.timestamp_unit)
+            .field("authtoken", &redact_option(&self.authtoken()))
+            .field("write_token", &redact_option(&self.write_token()))
+            .field("client", &self.client)
+            .field("last_response_headers", &self.last_response_headers)
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("max_item_count", &self.max_item_count)
+            .field("rate_limit_retries", &self.rate_limit_retries)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("login_timeout", &self.login_timeout)
+            .field("login_method", &self.login_method)
+            .field("write_token_in_query", &self.write_token_in_query)
+            .field("auto_login", &self.auto_login)
+            .field(
+                "default_subscription_folder",
+                &self.default_subscription_folder,
+            )
+            .field(
+                "include_all_direct_stream_ids",
+                &self.include_all_direct_stream_ids,
+            )
+            .field("stats", &self.stats)
+            .field("rate_limiter", &self.rate_limiter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The server software on the other end, which decides which non-standard features we can use
+pub enum Backend {
+    FreshRss,
+    Miniflux,
+    Inoreader,
+    TheOldReader,
+    Unknown,
+}
+
+impl Backend {
+    /// Guess the backend from the server URL, use [GoogleReaderBuilder::backend] if this gets it wrong.
+    ///
+    /// Miniflux is self-hosted on any domain and its URLs look like everyone else's, so it's never guessed,
+    /// set it with [GoogleReaderBuilder::backend].
+    pub fn detect(server_url: &Url) -> Self {
+        let host = server_url.host_str().unwrap_or_default();
+        if server_url.path().contains("greader.php") {
+            Backend::FreshRss
+        } else if host.ends_with("inoreader.com") {
+            Backend::Inoreader
+        } else if host.ends_with("theoldreader.com") {
+            Backend::TheOldReader
+        } else {
+            Backend::Unknown
+        }
+    }
+
+    /// The unit this backend expects for time bounds.
+    ///
+    /// FreshRSS, Miniflux, Inoreader and The Old Reader all follow the original API and use seconds. Miniflux
+    /// in particular treats microseconds as a time far in the future and returns nothing. If your server differs,
+    /// use [GoogleReaderBuilder::timestamp_unit].
+    pub fn timestamp_unit(&self) -> TimestampUnit {
+        TimestampUnit::Seconds
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The unit a server expects time bounds (`ot`, `nt`) in
+pub enum TimestampUnit {
+    /// Unix seconds, as the original API used
+    Seconds,
+    /// Unix microseconds
+    Microseconds,
+}
+
+impl TimestampUnit {
+    /// Format a time as a query parameter value, times before the epoch are clamped to it.
+    pub fn format(&self, time: SystemTime) -> String {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        match self {
+            TimestampUnit::Seconds => since_epoch.as_secs().to_string(),
+            TimestampUnit::Microseconds => since_epoch.as_micros().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Backend::FreshRss => "FreshRSS",
+            Backend::Miniflux => "Miniflux",
+            Backend::Inoreader => "Inoreader",
+            Backend::TheOldReader => "The Old Reader",
+            Backend::Unknown => "an unknown backend",
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Errors you might want to handle specifically, get at them with `anyhow::Error::downcast_ref::<Error>()`
+pub enum Error {
+    /// The server doesn't support what you asked for
+    NotSupported {
+        operation: &'static str,
+        backend: Backend,
+    },
+    /// The response body was bigger than [GoogleReaderBuilder::max_response_bytes]
+    ResponseTooLarge { limit: usize },
+    /// There's no auth token and we weren't allowed to log in, see [GoogleReaderBuilder::auto_login]
+    NotLoggedIn,
+    /// [GoogleReader::logout] wiped the password, so we can't log in again
+    LoggedOut,
+    /// The server sent back the continuation we just used, so paging would never finish
+    PaginationStuck { continuation: String },
+    /// The server rejected a saved continuation, start again from the beginning
+    ContinuationExpired { continuation: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotSupported { operation, backend } => {
+                write!(f, "{} isn't supported by {}", operation, backend)
+            }
+            Error::ResponseTooLarge { limit } => {
+                write!(f, "Response was bigger than the limit of {} bytes", limit)
+            }
+            Error::NotLoggedIn => write!(f, "Not logged in, call login() first"),
+            Error::LoggedOut => write!(
+                f,
+                "Logged out and the password is gone, create a new client to log in again"
+            ),
+            Error::PaginationStuck { continuation } => write!(
+                f,
+                "Server returned the continuation {:?} again, stopping rather than looping forever",
+                continuation
+            ),
+            Error::ContinuationExpired { continuation } => write!(
+                f,
+                "Server rejected the continuation {:?}, it's probably expired",
+                continuation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The tokens for a logged-in session, so you can persist them and skip logging in next time
+///
+/// `Debug` output doesn't include the tokens, only whether they're set.
+pub struct Session {
+    pub auth_token: Option<String>,
+    pub write_token: Option<String>,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("auth_token", &redact_option(&self.auth_token))
+            .field("write_token", &redact_option(&self.write_token))
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// What the server sent back from `accounts/ClientLogin`
+///
+/// Google's ClientLogin doesn't say when the token expires, but if your server sends anything extra (like an
+/// expiry) it's in [LoginResult::fields].
+pub struct LoginResult {
+    /// The `Auth` token, which we've already stored for the following requests
+    pub auth_token: String,
+    pub sid: Option<String>,
+    pub lsid: Option<String>,
+    /// Every `key=value` line in the response, including the ones above
+    pub fields: HashMap<String, String>,
+}
+
+impl fmt::Debug for LoginResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<&String> = self.fields.keys().collect();
+        keys.sort();
+        f.debug_struct("LoginResult")
+            .field("auth_token", &REDACTED)
+            .field("sid", &redact_option(&self.sid))
+            .field("lsid", &redact_option(&self.lsid))
+            .field("fields", &keys)
+            .finish()
+    }
+}
+
+/// Parse the `key=value` lines of a ClientLogin response, which has to include `Auth`.
+fn parse_login_response(body: &str) -> anyhow::Result<LoginResult> {
+    let fields: HashMap<String, String> = body
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    let auth_token = fields
+        .get("Auth")
+        .filter(|token| !token.is_empty())
+        .cloned()
+        .with_context(|| "No Auth token in the login response")?;
+    Ok(LoginResult {
+        auth_token,
+        sid: fields.get("SID").cloned(),
+        lsid: fields.get("LSID").cloned(),
+        fields,
+    })
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// How the client authenticates to the server
+pub enum AuthMode {
+    /// Log in with the username and password via `accounts/ClientLogin`, sending `Authorization: GoogleLogin auth=<token>`
+    #[default]
+    ClientLogin,
+    /// Use an OAuth2 access token, sending `Authorization: Bearer <token>`
+    ///
+    /// Set this with [GoogleReaderBuilder::bearer_token], the username and password aren't used.
+    Bearer,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// How the username and password are sent when logging in, see [GoogleReaderBuilder::login_method]
+pub enum LoginMethod {
+    /// `POST` them as a form body
+    #[default]
+    PostForm,
+    /// `GET` with them in the query string, for older servers which only accept that
+    GetQuery,
+}
+
+/// Builds a [GoogleReader], start with `GoogleReader::builder()`
+pub struct GoogleReaderBuilder {
+    username: String,
+    password: String,
+    server_url: String,
+    auth_mode: AuthMode,
+    bearer_token: Option<String>,
+    backend: Option<Backend>,
+    #[cfg(not(target_arch = "wasm32"))]
+    compression: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    redirect: Option<reqwest::redirect::Policy>,
+    api_path: String,
+    timestamp_unit: Option<TimestampUnit>,
+    max_response_bytes: usize,
+    max_item_count: usize,
+    rate_limit_retries: usize,
+    max_retry_after: Duration,
+    rate_limit: Option<f64>,
+    login_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    local_address: Option<std::net::IpAddr>,
+    #[cfg(not(target_arch = "wasm32"))]
+    accept_invalid_certs: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    root_certificates: Vec<reqwest::Certificate>,
+    login_method: LoginMethod,
+    write_token_in_query: bool,
+    auto_login: bool,
+    default_subscription_folder: Option<String>,
+    include_all_direct_stream_ids: bool,
+    collect_stats: bool,
+    user_agent: Option<String>,
     client: Option<Client>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
-/// A link to a resource
-pub struct Link {
-    pub href: String,
-}
+impl fmt::Debug for GoogleReaderBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("GoogleReaderBuilder");
+        debug
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("server_url", &self.server_url)
+            .field("auth_mode", &self.auth_mode)
+            .field("bearer_token", &redact_option(&self.bearer_token))
+            .field("backend", &self.backend)
+            .field("api_path", &self.api_path)
+            .field("timestamp_unit", &self.timestamp_unit)
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("max_item_count", &self.max_item_count)
+            .field("rate_limit_retries", &self.rate_limit_retries)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("rate_limit", &self.rate_limit)
+            .field("login_timeout", &self.login_timeout)
+            .field("login_method", &self.login_method)
+            .field("write_token_in_query", &self.write_token_in_query)
+            .field("auto_login", &self.auto_login)
+            .field(
+                "default_subscription_folder",
+                &self.default_subscription_folder,
+            )
+            .field(
+                "include_all_direct_stream_ids",
+                &self.include_all_direct_stream_ids,
+            )
+            .field("collect_stats", &self.collect_stats)
+            .field("user_agent", &self.user_agent)
+            .field("client", &self.client);
+        #[cfg(not(target_arch = "wasm32"))]
+        debug
+            .field("compression", &self.compression)
+            .field("redirect", &self.redirect)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("local_address", &self.local_address)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("root_certificates", &self.root_certificates);
+        debug.finish()
+    }
+}
+
+impl GoogleReaderBuilder {
+    /// Set the username and password used with `accounts/ClientLogin`
+    pub fn credentials(mut self, username: impl ToString, password: impl ToString) -> Self {
+        self.username = username.to_string();
+        self.password = password.to_string();
+        self
+    }
+
+    /// Authenticate with an OAuth2 bearer token instead of ClientLogin, for servers (like Inoreader) which don't support it.
+    pub fn bearer_token(mut self, token: impl ToString) -> Self {
+        self.auth_mode = AuthMode::Bearer;
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Set the backend, instead of guessing it from the server URL. Miniflux has to be set this way.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Ask for gzip or brotli compressed responses, on by default. Stream contents compress very well.
+    ///
+    /// Not available on WASM, where the browser decides.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set how redirects are followed, reqwest follows up to 10 by default.
+    ///
+    /// Security note: when a redirect goes to a different host or port (which includes `http` to `https`), reqwest
+    /// drops the `Authorization` header so your token isn't sent somewhere unexpected. That means requests fail
+    /// to authenticate after the redirect. It's better to fix the server URL than to work around this, and
+    /// `reqwest::redirect::Policy::none()` will surface the redirect as an error so you can spot it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn redirect(mut self, policy: reqwest::redirect::Policy) -> Self {
+        self.redirect = Some(policy);
+        self
+    }
+
+    /// Use an existing HTTP client, so several readers can share its connection pool.
+    ///
+    /// The client's own settings are used as-is, so [GoogleReaderBuilder::compression],
+    /// [GoogleReaderBuilder::redirect], [GoogleReaderBuilder::user_agent], [GoogleReaderBuilder::connect_timeout],
+    /// [GoogleReaderBuilder::local_address] and the TLS options are ignored.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the `User-Agent` sent with every request, including logging in, instead of reqwest's default.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// How to send the username and password to `accounts/ClientLogin`, a POST form by default.
+    pub fn login_method(mut self, login_method: LoginMethod) -> Self {
+        self.login_method = login_method;
+        self
+    }
+
+    /// Send the write token (`T`) in the query string of mutating requests instead of the form body, for the
+    /// few servers that only look for it there. Off by default.
+    pub fn write_token_in_query(mut self, write_token_in_query: bool) -> Self {
+        self.write_token_in_query = write_token_in_query;
+        self
+    }
+
+    /// Log in with the username and password when a request needs a token and there isn't one, on by default.
+    ///
+    /// Turn it off if the password shouldn't be used without you asking, requests then fail with
+    /// [Error::NotLoggedIn] until you call [GoogleReader::login] or restore a session.
+    pub fn auto_login(mut self, auto_login: bool) -> Self {
+        self.auto_login = auto_login;
+        self
+    }
+
+    /// Put feeds added with [GoogleReader::add_subscription] into this folder, rather than leaving them at the root.
+    ///
+    /// The folder can be a name (`Unsorted`) or a label ID (`user/-/label/Unsorted`).
+    pub fn default_subscription_folder(mut self, folder: Option<String>) -> Self {
+        self.default_subscription_folder = folder;
+        self
+    }
+
+    /// Send `includeAllDirectStreamIds=true` when listing subscriptions, which some servers need before they'll
+    /// include each feed's folders. On by default, turn it off for servers that choke on it.
+    pub fn include_all_direct_stream_ids(mut self, include_all_direct_stream_ids: bool) -> Self {
+        self.include_all_direct_stream_ids = include_all_direct_stream_ids;
+        self
+    }
+
+    /// Count requests and bytes received in [GoogleReader::stats], off by default.
+    pub fn collect_stats(mut self, collect_stats: bool) -> Self {
+        self.collect_stats = collect_stats;
+        self
+    }
+
+    /// Trust an extra root certificate, e.g. the one you used to sign your server's self-signed certificate.
+    ///
+    /// This is the safer way to connect to a server with a private CA, prefer it over
+    /// [GoogleReaderBuilder::danger_accept_invalid_certs].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Skip TLS certificate verification entirely, off by default.
+    ///
+    /// **This is insecure**: anyone who can intercept the connection can impersonate your server and read your
+    /// credentials. Only use it for testing, or if you really can't use [GoogleReaderBuilder::add_root_certificate].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Set the path to the API under the server URL, if your server or proxy doesn't mount it at `reader/api/0`.
+    ///
+    /// Login is still sent to `accounts/ClientLogin` under the server URL.
+    pub fn api_path(mut self, api_path: impl ToString) -> Self {
+        self.api_path = api_path.to_string();
+        self
+    }
+
+    /// Set the unit for time bounds in stream requests, instead of using the backend's default.
+    pub fn timestamp_unit(mut self, timestamp_unit: TimestampUnit) -> Self {
+        self.timestamp_unit = Some(timestamp_unit);
+        self
+    }
+
+    /// The biggest response body we'll read before giving up with [Error::ResponseTooLarge], 64MiB by default.
+    ///
+    /// This protects you from a misbehaving server sending an endless body. When streaming items it applies to
+    /// each page of results.
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// The most items [GoogleReader::feed_item_count] will count before stopping, 100,000 by default.
+    pub fn max_item_count(mut self, max_item_count: usize) -> Self {
+        self.max_item_count = max_item_count;
+        self
+    }
+
+    /// Give up on logging in if it takes longer than this, so a dead server fails fast. Not set by default.
+    ///
+    /// This covers the whole login request, including connecting.
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on connecting to the server if it takes longer than this, for every request. Not set by default.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send at most this many requests per second, on average, allowing bursts of up to a second's worth.
+    ///
+    /// Requests wait for their turn rather than failing, and clones of the reader share the limit. There's no
+    /// limit by default, and anything that isn't a positive number turns it off.
+    pub fn rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.rate_limit = (requests_per_second.is_finite() && requests_per_second > 0.0)
+            .then_some(requests_per_second);
+        self
+    }
+
+    /// Send requests from this local address, for picking the interface (and IPv4 or IPv6) on a machine with
+    /// more than one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn local_address(mut self, address: std::net::IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// How many times a request that gets `429 Too Many Requests` is retried after waiting for its
+    /// `Retry-After`, once by default. Set it to 0 to get the error straight away.
+    ///
+    /// Responses without a usable `Retry-After` header, or one longer than
+    /// [GoogleReaderBuilder::max_retry_after], aren't retried.
+    pub fn rate_limit_retries(mut self, retries: usize) -> Self {
+        self.rate_limit_retries = retries;
+        self
+    }
+
+    /// The longest `Retry-After` a rate limited request will wait before retrying, a minute by default.
+    /// If the server asks for longer, you get the `429` error instead.
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Build the HTTP client from the builder's settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new_client(&mut self) -> anyhow::Result<Client> {
+        let mut client = Client::builder()
+            .gzip(self.compression)
+            .brotli(self.compression)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        for certificate in std::mem::take(&mut self.root_certificates) {
+            client = client.add_root_certificate(certificate);
+        }
+        if let Some(redirect) = self.redirect.take() {
+            client = client.redirect(redirect);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        if let Some(local_address) = self.local_address {
+            client = client.local_address(local_address);
+        }
+        if let Some(user_agent) = self.user_agent.take() {
+            client = client.user_agent(user_agent);
+        }
+        client
+            .build()
+            .with_context(|| "Failed to build HTTP client")
+    }
+
+    /// Build the HTTP client, in the browser there's little to set up since it handles compression, TLS and
+    /// redirects itself.
+    #[cfg(target_arch = "wasm32")]
+    fn new_client(&mut self) -> anyhow::Result<Client> {
+        let mut client = Client::builder();
+        if let Some(user_agent) = self.user_agent.take() {
+            client = client.user_agent(user_agent);
+        }
+        client
+            .build()
+            .with_context(|| "Failed to build HTTP client")
+    }
+
+    /// Build the client, fails if the server URL doesn't parse.
+    pub fn build(mut self) -> anyhow::Result<GoogleReader> {
+        let server_url =
+            normalize_server_url(&self.server_url).with_context(|| "Failed to parse server URL")?;
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => self.new_client()?,
+        };
+        let backend = self.backend.unwrap_or_else(|| Backend::detect(&server_url));
+        Ok(GoogleReader {
+            username: self.username,
+            password: Arc::new(Mutex::new(Some(self.password))),
+            backend,
+            timestamp_unit: self
+                .timestamp_unit
+                .unwrap_or_else(|| backend.timestamp_unit()),
+            server_url,
+            api_path: self
+                .api_path
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| segment.to_string())
+                .collect(),
+            auth_mode: self.auth_mode,
+            authtoken: Arc::new(Mutex::new(self.bearer_token)),
+            write_token: Arc::new(Mutex::new(None)),
+            login_lock: Arc::new(tokio::sync::Mutex::new(None)),
+            client,
+            last_response_headers: None,
+            max_response_bytes: self.max_response_bytes,
+            max_item_count: self.max_item_count,
+            rate_limit_retries: self.rate_limit_retries,
+            max_retry_after: self.max_retry_after,
+            login_timeout: self.login_timeout,
+            login_method: self.login_method,
+            write_token_in_query: self.write_token_in_query,
+            auto_login: self.auto_login,
+            default_subscription_folder: self.default_subscription_folder,
+            include_all_direct_stream_ids: self.include_all_direct_stream_ids,
+            collect_stats: self.collect_stats,
+            stats: SyncStats::default(),
+            sync_started: None,
+            rate_limiter: self.rate_limit.map(|requests_per_second| {
+                Arc::new(Mutex::new(RateLimiter::new(requests_per_second)))
+            }),
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Readers for several accounts, keyed by account name, which share one HTTP client and its connection pool
+pub struct GoogleReaderPool {
+    client: Client,
+    readers: HashMap<String, GoogleReader>,
+}
+
+impl GoogleReaderPool {
+    /// Start an empty pool with a client that asks for compressed responses, like [GoogleReader::builder] does.
+    pub fn new() -> anyhow::Result<Self> {
+        let client = Client::builder();
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = client.gzip(true).brotli(true);
+        let client = client
+            .build()
+            .with_context(|| "Failed to build HTTP client")?;
+        Ok(Self::with_client(client))
+    }
+
+    /// Start an empty pool which shares the given client.
+    pub fn with_client(client: Client) -> Self {
+        GoogleReaderPool {
+            client,
+            readers: HashMap::new(),
+        }
+    }
+
+    /// Build a reader for an account with the pool's client, replacing any existing reader for that account.
+    pub fn add(
+        &mut self,
+        account: impl ToString,
+        builder: GoogleReaderBuilder,
+    ) -> anyhow::Result<()> {
+        let reader = builder.client(self.client.clone()).build()?;
+        self.readers.insert(account.to_string(), reader);
+        Ok(())
+    }
+
+    pub fn get(&self, account: &str) -> Option<&GoogleReader> {
+        self.readers.get(account)
+    }
+
+    pub fn get_mut(&mut self, account: &str) -> Option<&mut GoogleReader> {
+        self.readers.get_mut(account)
+    }
+
+    pub fn remove(&mut self, account: &str) -> Option<GoogleReader> {
+        self.readers.remove(account)
+    }
+
+    /// The account names in the pool, in no particular order.
+    pub fn accounts(&self) -> impl Iterator<Item = &str> {
+        self.readers.keys().map(String::as_str)
+    }
+
+    /// Log in to every account that uses ClientLogin, stopping at the first failure.
+    pub async fn login_all(&mut self) -> anyhow::Result<()> {
+        for (account, reader) in self.readers.iter_mut() {
+            if reader.auth_mode == AuthMode::ClientLogin {
+                reader
+                    .login()
+                    .await
+                    .with_context(|| format!("Failed to log in to {}", account))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// A link to a resource
+pub struct Link {
+    pub href: String,
+    /// The MIME type, e.g. `text/html` or `audio/mpeg`, if the server passes it through
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+}
+
+impl Link {
+    /// Whether the link is the given MIME type, ignoring case and parameters like `charset`.
+    pub fn is_type(&self, mime: &str) -> bool {
+        self.kind.as_deref().is_some_and(|kind| {
+            kind.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case(mime)
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// Item Summary
+pub struct Summary {
+    pub content: Option<String>,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// Feed Item
+pub struct Item {
+    /// Usually the long form, `tag:google.com,2005:reader/item/<hex>`, see [Item::short_id] for the number
+    pub id: String,
+    #[serde(alias = "crawlTimeMsec")]
+    pub crawl_time_msec: Option<String>,
+    #[serde(alias = "timestampUsec")]
+    pub timestamp_usec: Option<String>,
+    /// Seconds since the epoch, servers that send floats or strings are tolerated
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub updated: Option<usize>,
+    /// Seconds since the epoch, like [Item::updated]
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub published: Option<usize>,
+    /// Empty if the server left it out, which happens with microblog-style feeds
+    #[serde(default)]
+    pub title: String,
+    pub canonical: Vec<Link>,
+    pub alternate: Vec<Link>,
+    /// The item's own link, which some servers use for stable per-item URLs
+    #[serde(default, rename = "self")]
+    pub self_link: Vec<Link>,
+    #[serde(default)]
+    pub related: Vec<Link>,
+    /// Text direction, e.g. `ltr`
+    #[serde(default)]
+    pub direction: Option<String>,
+    /// Category stream IDs, some servers send `{"id": ..., "label": ...}` objects which are turned into IDs
+    #[serde(deserialize_with = "deserialize_categories")]
+    pub categories: Vec<String>,
+    pub origin: HashMap<String, String>,
+    pub summary: Summary,
+    /// Notes left on the item, only some servers keep these, see [GoogleReader::get_item_annotations]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Who liked the item, if you asked for [StreamOptions::likes] and the server supports it
+    #[serde(rename = "likingUsers", default)]
+    pub liking_users: Vec<LikingUser>,
+    #[serde(rename = "likingUsersCount", default)]
+    pub liking_users_count: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// Someone who liked an item
+pub struct LikingUser {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// A note on an item
+pub struct Annotation {
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A parsed item category, from [Item::parsed_categories]
+pub enum CategoryRef {
+    /// A state like `read` or `starred`, from `user/-/state/com.google/<state>`
+    ///
+    /// States outside the `com.google` namespace keep their namespace, e.g. `org.freshrss/example`.
+    State(String),
+    /// A label (folder or tag), from `user/-/label/<label>`
+    Label(String),
+    /// Anything else, as-is
+    Other(String),
+}
+
+impl CategoryRef {
+    /// Parse a category stream ID, the user part can be `-` or the user's ID.
+    pub fn parse(category: &str) -> Self {
+        let rest = category
+            .strip_prefix("user/")
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(_user, rest)| rest);
+        match rest {
+            Some(rest) => {
+                if let Some(state) = rest.strip_prefix("state/") {
+                    let state = state.strip_prefix("com.google/").unwrap_or(state);
+                    CategoryRef::State(state.to_string())
+                } else if let Some(label) = rest.strip_prefix("label/") {
+                    CategoryRef::Label(label.to_string())
+                } else {
+                    CategoryRef::Other(category.to_string())
+                }
+            }
+            None => CategoryRef::Other(category.to_string()),
+        }
+    }
+}
+
+impl Item {
+    /// Is the item in the given `user/-/state/com.google/...` state, e.g. `starred`.
+    fn has_state(&self, state: &str) -> bool {
+        self.parsed_categories()
+            .iter()
+            .any(|category| matches!(category, CategoryRef::State(found) if found == state))
+    }
+
+    /// Every category the item's in, parsed into states, labels and everything else.
+    pub fn parsed_categories(&self) -> Vec<CategoryRef> {
+        self.categories
+            .iter()
+            .map(|category| CategoryRef::parse(category))
+            .collect()
+    }
+
+    /// The names of the labels (folders and tags) the item's in.
+    pub fn labels(&self) -> Vec<String> {
+        self.parsed_categories()
+            .into_iter()
+            .filter_map(|category| match category {
+                CategoryRef::Label(label) => Some(label),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Has the item been read
+    pub fn is_read(&self) -> bool {
+        self.has_state("read")
+    }
+
+    /// The item's numeric ID, from either the long form (`tag:google.com,2005:reader/item/<hex>`) or a
+    /// short decimal form.
+    ///
+    /// Short IDs can be negative, since Google Reader treated them as signed 64-bit integers, these are
+    /// returned as the same bits as a `u64` so they match the hex in the long form. This is always 64 bits wide,
+    /// so it's safe on 32-bit targets.
+    pub fn short_id(&self) -> Option<u64> {
+        parse_item_id(&self.id)
+    }
+
+    /// The stream ID of the feed the item came from, e.g. `feed/1`.
+    pub fn origin_stream_id(&self) -> Option<&str> {
+        self.origin.get("streamId").map(String::as_str)
+    }
+
+    /// The title of the feed the item came from.
+    pub fn origin_title(&self) -> Option<&str> {
+        self.origin.get("title").map(String::as_str)
+    }
+
+    /// Whether this is the item with the given ID, which can be in either the long or short form.
+    pub fn has_id(&self, item_id: &str) -> bool {
+        match (self.short_id(), parse_item_id(item_id)) {
+            (Some(id), Some(other)) => id == other,
+            _ => self.id == item_id,
+        }
+    }
+
+    /// Is the item starred
+    pub fn is_starred(&self) -> bool {
+        self.has_state("starred")
+    }
+
+    /// The item's link, preferring the canonical link, then a `text/html` (or untyped) alternate, then any
+    /// alternate.
+    pub fn url(&self) -> Option<&str> {
+        self.canonical
+            .first()
+            .or_else(|| {
+                self.alternate
+                    .iter()
+                    .find(|link| link.kind.is_none() || link.is_type("text/html"))
+            })
+            .or_else(|| self.alternate.first())
+            .map(|link| link.href.as_str())
+    }
+
+    /// The first alternate link of the given MIME type, e.g. `audio/mpeg` for a podcast episode.
+    pub fn alternate_by_type(&self, mime: &str) -> Option<&Link> {
+        self.alternate.iter().find(|link| link.is_type(mime))
+    }
+
+    /// The item's content with the HTML tags stripped and entities decoded, for notifications and search.
+    ///
+    /// Block elements like `<p>` and `<br>` become line breaks, and scripts and styles are dropped. This isn't
+    /// a sanitizer, don't put the result back into a page as HTML.
+    pub fn plain_text_content(&self) -> Option<String> {
+        self.summary.content.as_deref().map(html_to_text)
+    }
+
+    /// Just the bits of the item you'd need for a list view, without the content.
+    pub fn display_summary(&self) -> ItemSummary {
+        ItemSummary {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            url: self.url().map(str::to_string),
+            feed_title: self.origin_title().map(str::to_string),
+            published: self.published,
+            is_read: self.is_read(),
+            is_starred: self.is_starred(),
+        }
+    }
+
+    /// When the item was published.
+    pub fn published_time(&self) -> Option<SystemTime> {
+        UNIX_EPOCH.checked_add(Duration::from_secs(self.published? as u64))
+    }
+
+    /// When the item was last updated.
+    pub fn updated_time(&self) -> Option<SystemTime> {
+        UNIX_EPOCH.checked_add(Duration::from_secs(self.updated? as u64))
+    }
+
+    /// When the item was published, as a chrono time.
+    #[cfg(feature = "chrono")]
+    pub fn published_datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(i64::try_from(self.published?).ok()?, 0)
+    }
+
+    /// When the item was last updated, as a chrono time.
+    #[cfg(feature = "chrono")]
+    pub fn updated_datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(i64::try_from(self.updated?).ok()?, 0)
+    }
+
+    /// When the item was published, as a `time` time.
+    #[cfg(feature = "time")]
+    pub fn published_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        time::OffsetDateTime::from_unix_timestamp(i64::try_from(self.published?).ok()?).ok()
+    }
+
+    /// When the item was last updated, as a `time` time.
+    #[cfg(feature = "time")]
+    pub fn updated_offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        time::OffsetDateTime::from_unix_timestamp(i64::try_from(self.updated?).ok()?).ok()
+    }
+
+    /// The item's timestamp in microseconds, falling back to the published time if the server didn't send one.
+    pub fn timestamp_micros(&self) -> Option<u64> {
+        match &self.timestamp_usec {
+            Some(timestamp) => timestamp.parse().ok(),
+            None => (self.published? as u64).checked_mul(1_000_000),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A lightweight view of an [Item], from [Item::display_summary]
+pub struct ItemSummary {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub feed_title: Option<String>,
+    /// Seconds since the epoch
+    pub published: Option<usize>,
+    pub is_read: bool,
+    pub is_starred: bool,
+}
+
+#[derive(Debug)]
+/// Everything an app needs on launch, from [GoogleReader::initial_sync]
+pub struct InitialSync {
+    pub subscriptions: Vec<Subscription>,
+    pub tags: Vec<Tag>,
+    /// The total unread count, see [GoogleReader::unread_count]
+    pub unread_count: usize,
+    /// The first page of unread items, carry on from its continuation with [GoogleReader::get_unread_items]
+    pub unread: Response,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// How item states changed on the server, from [GoogleReader::state_changes_since]
+pub struct StateDiff {
+    /// Read on the server but not in your cache
+    pub newly_read: Vec<String>,
+    /// Read in your cache but not on the server any more
+    pub newly_unread: Vec<String>,
+    pub newly_starred: Vec<String>,
+    pub newly_unstarred: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// A reference to an item, as returned by `stream/items/ids`
+pub struct ItemRef {
+    /// The item's short (decimal) ID
+    pub id: String,
+    #[serde(rename = "timestampUsec", default)]
+    pub timestamp_usec: Option<String>,
+    #[serde(rename = "directStreamIds", default)]
+    pub direct_stream_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `stream/items/ids`, or `search/items/ids`
+pub struct ItemIds {
+    /// `results` in search responses
+    #[serde(rename = "itemRefs", alias = "results", default)]
+    pub item_refs: Vec<ItemRef>,
+    pub continuation: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from the API
+pub struct Response {
+    pub id: String,
+    /// The stream's title, usually only sent for feed streams
+    #[serde(default)]
+    pub title: Option<String>,
+    pub items: Vec<Item>,
+    pub updated: usize,
+    pub continuation: Option<String>,
+}
+
+/// Helpers for working with the items you've already fetched, these don't touch the network.
+impl Response {
+    /// Count the items matching a predicate.
+    pub fn count_where(&self, pred: impl Fn(&Item) -> bool) -> usize {
+        self.items.iter().filter(|item| pred(item)).count()
+    }
+
+    /// The number of starred items.
+    pub fn starred_count(&self) -> usize {
+        self.count_where(Item::is_starred)
+    }
+
+    /// The items which came from the given feed, e.g. `feed/https://example.com/feed.xml`.
+    pub fn by_origin(&self, stream_id: &str) -> Vec<&Item> {
+        self.items
+            .iter()
+            .filter(|item| item.origin_stream_id() == Some(stream_id))
+            .collect()
+    }
+
+    /// The items by the given author, ignoring case and surrounding whitespace.
+    pub fn by_author(&self, author: &str) -> Vec<&Item> {
+        let author = author.trim();
+        self.items
+            .iter()
+            .filter(|item| {
+                item.summary
+                    .author
+                    .as_deref()
+                    .is_some_and(|found| found.trim().eq_ignore_ascii_case(author))
+            })
+            .collect()
+    }
+
+    /// The items grouped by the feed they came from, keyed by its stream ID. Items without an origin are left out.
+    ///
+    /// Each group keeps the response's order, use [Item::origin_title] on any of them for the feed's title.
+    pub fn group_by_origin(&self) -> HashMap<String, Vec<&Item>> {
+        let mut groups: HashMap<String, Vec<&Item>> = HashMap::new();
+        for item in &self.items {
+            if let Some(stream_id) = item.origin_stream_id() {
+                groups.entry(stream_id.to_string()).or_default().push(item);
+            }
+        }
+        groups
+    }
+
+    /// The items with duplicates (the same [Item::url]) removed, keeping the earliest published copy.
+    ///
+    /// Items without a URL are all kept, and the result is in the response's order.
+    pub fn deduplicate_by_url(&self) -> Vec<&Item> {
+        let mut earliest: HashMap<&str, &Item> = HashMap::new();
+        for item in &self.items {
+            if let Some(url) = item.url() {
+                let kept = earliest.entry(url).or_insert(item);
+                // items without a published time lose to ones with
+                if item.published.unwrap_or(usize::MAX) < kept.published.unwrap_or(usize::MAX) {
+                    *kept = item;
+                }
+            }
+        }
+        self.items
+            .iter()
+            .filter(|item| match item.url() {
+                Some(url) => std::ptr::eq(earliest[url], *item),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A stream ID, which is anything you can fetch items from or tag items with
+///
+/// It's a `Display`, so it can be passed anywhere that takes a stream ID. The built-in states are also
+/// available as `&str` constants, e.g. [StreamId::READ].
+pub struct StreamId(String);
+
+impl StreamId {
+    /// Every item from every subscription
+    pub const READING_LIST: &'static str = "user/-/state/com.google/reading-list";
+    pub const READ: &'static str = "user/-/state/com.google/read";
+    pub const STARRED: &'static str = "user/-/state/com.google/starred";
+    /// Items the user has explicitly kept unread
+    pub const KEPT_UNREAD: &'static str = "user/-/state/com.google/kept-unread";
+    /// Items the user has shared
+    pub const BROADCAST: &'static str = "user/-/state/com.google/broadcast";
+
+    pub fn reading_list() -> Self {
+        StreamId(Self::READING_LIST.to_string())
+    }
+
+    pub fn read() -> Self {
+        StreamId(Self::READ.to_string())
+    }
+
+    pub fn starred() -> Self {
+        StreamId(Self::STARRED.to_string())
+    }
+
+    pub fn kept_unread() -> Self {
+        StreamId(Self::KEPT_UNREAD.to_string())
+    }
+
+    pub fn broadcast() -> Self {
+        StreamId(Self::BROADCAST.to_string())
+    }
+
+    /// A feed, from its URL (`feed/https://example.com/feed.xml`) or the server's feed ID if it has its own
+    /// scheme (FreshRSS uses `feed/1`)
+    pub fn feed(feed: impl fmt::Display) -> Self {
+        StreamId(format!("feed/{}", feed))
+    }
+
+    /// A label (folder or tag), accepts either a bare name (`Rust`) or a full label ID (`user/-/label/Rust`).
+    pub fn label(label: &str) -> Self {
+        StreamId(label_stream_id(label))
+    }
+
+    /// Any other stream ID, as-is.
+    pub fn new(stream_id: impl ToString) -> Self {
+        StreamId(stream_id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for StreamId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<StreamId> for String {
+    fn from(stream_id: StreamId) -> Self {
+        stream_id.0
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// The order items are returned in from a stream
+pub enum StreamOrder {
+    /// `r=n`, the default
+    #[default]
+    NewestFirst,
+    /// `r=o`
+    ///
+    /// Some FreshRSS versions ignore `r=o` unless a start time (`ot=`) is also sent, so we always send `ot=0`
+    /// along with it. If you're still getting newest-first results, check your server version.
+    OldestFirst,
+}
+
+impl StreamOrder {
+    /// The value of the `r` query parameter
+    fn as_param(&self) -> &'static str {
+        match self {
+            StreamOrder::NewestFirst => "n",
+            StreamOrder::OldestFirst => "o",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// Options for fetching stream contents
+pub struct StreamOptions {
+    pub order: StreamOrder,
+    /// The maximum number of items to return per page (`n`), the server picks if this isn't set
+    pub count: Option<usize>,
+    /// Only return items newer than this (`ot`)
+    pub start_time: Option<SystemTime>,
+    /// Only return items older than this (`nt`)
+    pub end_time: Option<SystemTime>,
+    /// Exclude items in this stream (`xt`), e.g. `user/-/state/com.google/read`
+    pub exclude: Option<String>,
+    /// Ask for each item's [Item::annotations] (`annotations=true`), off by default
+    pub annotations: bool,
+    /// Ask for who liked each item (`likes=true`), see [Item::liking_users], off by default
+    pub likes: bool,
+    /// Extra query parameters for server-specific features, added after the standard ones
+    ///
+    /// Parameters the crate already sent are skipped rather than sent twice, so these can't override them.
+    pub extra_params: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// How much of each item [GoogleReader::get_stream] fetches
+pub enum Detail {
+    /// Just IDs and timestamps from `stream/items/ids`, a fraction of the size of the full contents
+    IdsOnly,
+    /// Everything from `stream/contents`
+    #[default]
+    Full,
+}
+
+#[derive(Debug)]
+/// A page of a stream from [GoogleReader::get_stream], in the [Detail] that was asked for
+pub enum StreamPage {
+    Ids(ItemIds),
+    Full(Response),
+}
+
+impl StreamPage {
+    /// The continuation for fetching the next page, if there is one.
+    pub fn continuation(&self) -> Option<&str> {
+        match self {
+            StreamPage::Ids(ids) => ids.continuation.as_deref(),
+            StreamPage::Full(response) => response.continuation.as_deref(),
+        }
+    }
+
+    /// The number of items on this page.
+    pub fn len(&self) -> usize {
+        match self {
+            StreamPage::Ids(ids) => ids.item_refs.len(),
+            StreamPage::Full(response) => response.items.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// A folder (label) a subscription is in
+pub struct Category {
+    /// e.g. `user/-/label/Rust`
+    pub id: String,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// A tag from `tag/list`, which covers folders, labels and the built-in states
+pub struct Tag {
+    /// e.g. `user/-/label/Rust` or `user/-/state/com.google/starred`
+    pub id: String,
+    /// Decides the tag's position in the user's ordering, see [GoogleReader::list_tags_sorted]
+    #[serde(default)]
+    pub sortid: Option<String>,
+    /// `folder` or `tag` on servers which tell them apart
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+}
+
+impl Tag {
+    /// The tag's name if it's a label (folder or tag), rather than a state.
+    pub fn label(&self) -> Option<&str> {
+        self.id
+            .strip_prefix("user/")
+            .and_then(|rest| rest.split_once('/'))
+            .and_then(|(_user, rest)| rest.strip_prefix("label/"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A folder and the feeds in it, from [GoogleReader::subscription_tree]
+pub struct FolderNode {
+    /// The folder's name, empty for the node holding feeds that aren't in any folder
+    pub name: String,
+    pub feeds: Vec<Subscription>,
+    /// Always empty with current servers, since Google Reader folders can't be nested
+    pub subfolders: Vec<FolderNode>,
+}
+
+/// Group subscriptions into folders, in the order the tags list them, see [GoogleReader::subscription_tree].
+fn folder_tree(subscriptions: Vec<Subscription>, tags: &[Tag]) -> Vec<FolderNode> {
+    let mut nodes: Vec<FolderNode> = tags
+        .iter()
+        .filter(|tag| tag.kind.as_deref() != Some("tag"))
+        .filter_map(Tag::label)
+        .map(|name| FolderNode {
+            name: name.to_string(),
+            feeds: Vec::new(),
+            subfolders: Vec::new(),
+        })
+        .collect();
+    let mut unfiled = Vec::new();
+    for subscription in subscriptions {
+        let folders = subscription.folders();
+        if folders.is_empty() {
+            unfiled.push(subscription);
+            continue;
+        }
+        for folder in folders {
+            let index = match nodes.iter().position(|node| node.name == folder) {
+                Some(index) => index,
+                // the tag list doesn't always include every folder
+                None => {
+                    nodes.push(FolderNode {
+                        name: folder,
+                        feeds: Vec::new(),
+                        subfolders: Vec::new(),
+                    });
+                    nodes.len() - 1
+                }
+            };
+            nodes[index].feeds.push(subscription.clone());
+        }
+    }
+    if !unfiled.is_empty() {
+        nodes.push(FolderNode {
+            name: String::new(),
+            feeds: unfiled,
+            subfolders: Vec::new(),
+        });
+    }
+    nodes
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `tag/list`
+struct TagList {
+    tags: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+/// Someone from `friend/list`, which includes the user themselves
+pub struct Friend {
+    #[serde(default)]
+    pub user_ids: Vec<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Their shared items, e.g. `user/123/state/com.google/broadcast`
+    #[serde(default)]
+    pub stream: Option<String>,
+    /// The user themselves
+    #[serde(default)]
+    pub is_me: bool,
+    #[serde(default)]
+    pub is_hidden: bool,
+    /// How they're related to the user, e.g. following or followed by, the values are up to the server
+    #[serde(default)]
+    pub types: Vec<u32>,
+    /// Server-specific bit flags about the relationship
+    #[serde(default)]
+    pub flags: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `friend/list`
+struct FriendList {
+    #[serde(default)]
+    friends: Vec<Friend>,
+}
+
+/// Sort tags into the user's order.
+///
+/// Google Reader kept the order in the root stream's `subscription-ordering` preference, which is the tags'
+/// sort IDs (eight characters each) run together. Tags that aren't in it, or everything if there's no ordering,
+/// are sorted by sort ID after the ordered ones, then tags without one.
+fn sort_tags(tags: &mut [Tag], ordering: Option<&str>) {
+    let position = |tag: &Tag| -> Option<usize> {
+        let sortid = tag.sortid.as_deref()?;
+        let ordering = ordering?;
+        (0..ordering.len())
+            .step_by(8)
+            .position(|start| ordering.get(start..start + 8) == Some(sortid))
+    };
+    tags.sort_by(|a, b| {
+        let (a_position, b_position) = (position(a), position(b));
+        match (a_position, b_position) {
+            (Some(a_position), Some(b_position)) => a_position.cmp(&b_position),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            // tags without a sort ID go last
+            (None, None) => {
+                (a.sortid.is_none(), &a.sortid, &a.id).cmp(&(b.sortid.is_none(), &b.sortid, &b.id))
+            }
+        }
+    });
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+/// A feed the user is subscribed to
+pub struct Subscription {
+    /// The stream ID, e.g. `feed/1` on FreshRSS or `feed/https://example.com/feed.xml` on others
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    /// The feed URL
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The website URL
+    #[serde(alias = "htmlUrl", default)]
+    pub html_url: Option<String>,
+    #[serde(alias = "iconUrl", default)]
+    pub icon_url: Option<String>,
+    /// The feed's WebSub (PubSubHubbub) hub, if it advertises one
+    ///
+    /// Few servers pass this through from the feed, so it's usually `None` even when the feed has a hub.
+    #[serde(default, deserialize_with = "deserialize_link_href")]
+    pub hub: Option<String>,
+    /// The feed's own (`rel="self"`) URL as advertised by the feed, which WebSub uses as the topic
+    ///
+    /// Like [Subscription::hub] this depends on the server.
+    #[serde(default, rename = "self", deserialize_with = "deserialize_link_href")]
+    pub self_url: Option<String>,
+}
+
+/// Timestamps in seconds come as integers, floats (`1700000000.0`) or strings of either, floats are truncated.
+///
+/// Anything else, like a negative number or a string that isn't a number, is treated as missing.
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    fn seconds(value: f64) -> Option<usize> {
+        (value.is_finite() && value >= 0.0).then_some(value as usize)
+    }
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(number) => match number.as_u64() {
+            Some(number) => usize::try_from(number).ok(),
+            None => number.as_f64().and_then(seconds),
+        },
+        serde_json::Value::String(number) => match number.trim().parse::<usize>() {
+            Ok(number) => Some(number),
+            Err(_) => number.trim().parse::<f64>().ok().and_then(seconds),
+        },
+        _ => None,
+    })
+}
+
+/// Servers send links as a bare URL, a [Link], or a list of them, this takes the first URL from any of those.
+fn deserialize_link_href<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    fn href(value: serde_json::Value) -> Option<String> {
+        match value {
+            serde_json::Value::String(href) => Some(href),
+            serde_json::Value::Object(mut link) => link.remove("href").and_then(href),
+            serde_json::Value::Array(links) => links.into_iter().find_map(href),
+            _ => None,
+        }
+    }
+    Ok(href(serde_json::Value::deserialize(deserializer)?))
+}
+
+/// Item categories come as stream ID strings or as [Category]-style objects, this turns either into IDs.
+///
+/// Objects without an `id` fall back to their `label` as a folder, anything else is skipped.
+fn deserialize_categories<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .filter_map(|value| match value {
+            serde_json::Value::String(id) => Some(id),
+            serde_json::Value::Object(mut category) => match category.remove("id") {
+                Some(serde_json::Value::String(id)) => Some(id),
+                _ => match category.remove("label") {
+                    Some(serde_json::Value::String(label)) => Some(label_stream_id(&label)),
+                    _ => None,
+                },
+            },
+            _ => None,
+        })
+        .collect())
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Which items a batch operation like [GoogleReader::mark_items_read] changed, in the order they were given
+///
+/// Servers answer `OK` (or don't) for a whole request rather than per item, so items succeed or fail together
+/// in batches of up to [EDIT_TAG_BATCH_SIZE] and share the failure reason.
+pub struct BatchResult {
+    pub succeeded: Vec<String>,
+    /// The items that weren't changed, with why
+    pub failed: Vec<(String, String)>,
+}
+
+impl BatchResult {
+    /// Whether every item was changed.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What we found out about a feed URL, from [GoogleReader::probe_feed]
+pub struct FeedProbe {
+    /// The stream ID for the feed
+    pub stream_id: String,
+    /// Whether the server could fetch and parse the feed
+    pub is_feed: bool,
+    /// The feed's title, if the server told us
+    pub title: Option<String>,
+    pub already_subscribed: bool,
+}
+
+impl Subscription {
+    /// Where to get the feed's icon, the server's `iconUrl` or failing that `/favicon.ico` on the website.
+    pub fn favicon_url(&self) -> Option<Url> {
+        if let Some(icon_url) = &self.icon_url {
+            if let Ok(icon_url) = Url::parse(icon_url) {
+                return Some(icon_url);
+            }
+        }
+        let html_url = Url::parse(self.html_url.as_ref()?).ok()?;
+        html_url.join("/favicon.ico").ok()
+    }
+
+    /// The names of the folders the feed's in, without the `user/-/label/` prefix, empty if it isn't in any.
+    pub fn folders(&self) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter_map(|category| match CategoryRef::parse(&category.id) {
+                CategoryRef::Label(label) => Some(label),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Reads a response body, stopping with [Error::ResponseTooLarge] as soon as it's bigger than `limit`.
+async fn read_limited(res: reqwest::Response, limit: usize) -> anyhow::Result<Vec<u8>> {
+    if res
+        .content_length()
+        .is_some_and(|content_length| content_length > limit as u64)
+    {
+        return Err(Error::ResponseTooLarge { limit }.into());
+    }
+    let mut body = Vec::new();
+    let mut chunks = body_stream(res);
+    while let Some(chunk) = chunks
+        .next()
+        .await
+        .transpose()
+        .with_context(|| "Failed to read response body")?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(Error::ResponseTooLarge { limit }.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Download a favicon, without the API auth headers since it's probably not on the API server.
+async fn download_favicon(client: Client, url: Url, limit: usize) -> anyhow::Result<Vec<u8>> {
+    trace!("favicon url: {}", url);
+    let res = client
+        .get(url.clone())
+        .send()
+        .await
+        .with_context(|| format!("Failed to send favicon request to {}", redact_url(&url)))?
+        .error_for_status()
+        .with_context(|| format!("Failed to get favicon from {}", redact_url(&url)))?;
+    read_limited(res, limit)
+        .await
+        .with_context(|| "Failed to get favicon response body")
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `subscription/list`
+struct SubscriptionList {
+    subscriptions: Vec<Subscription>,
+    /// Only sent by servers which page the subscription list
+    #[serde(default)]
+    continuation: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// The parts of a `stream/contents` response that aren't items
+struct StreamEnvelope {
+    #[serde(default)]
+    continuation: Option<String>,
+}
+
+#[derive(Debug, Default)]
+/// Pulls the items out of a `stream/contents` response as the bytes arrive, so only one item needs to be in
+/// memory at a time.
+struct ItemSplitter {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Inside the top-level `items` array
+    in_items: bool,
+    /// The top-level string we're in the middle of reading, to spot the `items` key
+    key: Option<Vec<u8>>,
+    last_key: Vec<u8>,
+    /// Everything outside the `items` array, which is parsed at the end for the continuation
+    envelope: Vec<u8>,
+    /// The item we're in the middle of reading
+    item: Vec<u8>,
+}
+
+impl ItemSplitter {
+    /// Feed in the next chunk of the body, returns any items it completed.
+    fn push(&mut self, chunk: &[u8]) -> anyhow::Result<Vec<Item>> {
+        let mut items = Vec::new();
+        for &byte in chunk {
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    if let Some(key) = self.key.take() {
+                        self.last_key = key;
+                    }
+                } else if let Some(key) = self.key.as_mut() {
+                    key.push(byte);
+                }
+                self.record(byte);
+                continue;
+            }
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    if !self.in_items && self.depth == 1 {
+                        self.key = Some(Vec::new());
+                    }
+                    self.record(byte);
+                }
+                b'[' if !self.in_items && self.depth == 1 && self.last_key == b"items" => {
+                    self.in_items = true;
+                    self.depth += 1;
+                    self.envelope.extend_from_slice(b"[]");
+                }
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    self.record(byte);
+                }
+                b']' if self.in_items && self.depth == 2 => {
+                    self.in_items = false;
+                    self.depth -= 1;
+                }
+                b'}' | b']' => {
+                    self.record(byte);
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.in_items && self.depth == 2 {
+                        let item: Item = serde_json::from_slice(&self.item)
+                            .with_context(|| "Failed to parse item from stream contents")?;
+                        self.item.clear();
+                        items.push(item);
+                    }
+                }
+                _ => self.record(byte),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Keep a byte in the item or the envelope, depending on where we are. Separators between items are dropped.
+    fn record(&mut self, byte: u8) {
+        if !self.in_items {
+            self.envelope.push(byte);
+        } else if self.depth >= 3 {
+            self.item.push(byte);
+        }
+    }
+
+    /// Call at the end of the body, returns the continuation if there's another page.
+    fn finish(self) -> anyhow::Result<Option<String>> {
+        let envelope: StreamEnvelope = serde_json::from_slice(&self.envelope)
+            .with_context(|| "Failed to parse stream contents response body")?;
+        Ok(envelope.continuation)
+    }
+}
+
+/// Pages through a stream one page at a time, from [GoogleReader::resume_unread_fetch]
+///
+/// Save [Paginator::continuation] after each page and you can pick up from there later, even from another
+/// process.
+pub struct Paginator<'a, T> {
+    reader: &'a mut GoogleReader,
+    stream_id: String,
+    options: StreamOptions,
+    continuation: Option<String>,
+    done: bool,
+    _item: PhantomData<T>,
+}
+
+impl<T> fmt::Debug for Paginator<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("stream_id", &self.stream_id)
+            .field("options", &self.options)
+            .field("continuation", &self.continuation)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Paginator<'_, T> {
+    /// The continuation for the next page, `None` once every page has been fetched.
+    pub fn continuation(&self) -> Option<&str> {
+        self.continuation.as_deref()
+    }
+
+    /// Whether every page has been fetched.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Paginator<'_, Item> {
+    /// Fetches the next page, `None` once there aren't any more.
+    ///
+    /// If the server refuses the continuation outright (`400`, `404` or `410`) this fails with
+    /// [Error::ContinuationExpired], and you'll need to start the fetch again.
+    pub async fn next_page(&mut self) -> anyhow::Result<Option<Vec<Item>>> {
+        if self.done {
+            return Ok(None);
+        }
+        let response = match self
+            .reader
+            .get_stream_contents(
+                &self.stream_id,
+                self.continuation.clone(),
+                self.options.clone(),
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                let rejected = error_status(&err)
+                    .is_some_and(|status| matches!(status.as_u16(), 400 | 404 | 410));
+                return match (&self.continuation, rejected) {
+                    (Some(continuation), true) => Err(Error::ContinuationExpired {
+                        continuation: continuation.clone(),
+                    }
+                    .into()),
+                    _ => Err(err),
+                };
+            }
+        };
+        self.continuation = next_continuation(self.continuation.as_deref(), response.continuation)?;
+        self.done = self.continuation.is_none();
+        Ok(Some(response.items))
+    }
+}
+
+/// The state behind [GoogleReader::stream_items]
+struct ItemStreamState<'a> {
+    reader: &'a mut GoogleReader,
+    stream_id: String,
+    options: StreamOptions,
+    /// The page we're reading, if we've started one
+    response: Option<BodyStream>,
+    splitter: ItemSplitter,
+    /// How much of the current page we've read, to enforce `max_response_bytes`
+    page_bytes: usize,
+    pending: VecDeque<Item>,
+    continuation: Option<String>,
+    done: bool,
+}
+
+impl ItemStreamState<'_> {
+    /// Read the next chunk of the current page, starting the next page if needed.
+    async fn next_chunk(&mut self) -> anyhow::Result<()> {
+        let response = match self.response.as_mut() {
+            Some(response) => response,
+            None => {
+                let response = self
+                    .reader
+                    .send_stream_contents(
+                        &self.stream_id,
+                        self.continuation.as_deref(),
+                        &self.options,
+                    )
+                    .await?;
+                self.response.insert(body_stream(response))
+            }
+        };
+
+        match response
+            .next()
+            .await
+            .transpose()
+            .with_context(|| "Failed to read stream contents response body")?
+        {
+            Some(chunk) => {
+                self.page_bytes += chunk.len();
+                self.reader.record_bytes_received(chunk.len());
+                let limit = self.reader.max_response_bytes;
+                if self.page_bytes > limit {
+                    return Err(Error::ResponseTooLarge { limit }.into());
+                }
+                self.pending.extend(self.splitter.push(&chunk)?);
+            }
+            None => {
+                self.response = None;
+                self.page_bytes = 0;
+                let next = std::mem::take(&mut self.splitter).finish()?;
+                self.continuation = next_continuation(self.continuation.as_deref(), next)?;
+                self.done = self.continuation.is_none();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// A single entry from `preference/list`
+struct Preference {
+    id: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `preference/stream/list`, preferences keyed by stream ID
+struct StreamPreferenceList {
+    #[serde(default)]
+    streamprefs: HashMap<String, Vec<Preference>>,
+}
+
+/// Replace the user ID in a `user/<id>/...` stream ID with `-`, since servers list stream preferences against
+/// the real user ID but everyone asks for `user/-/...`.
+fn anonymous_stream_id(stream_id: &str) -> String {
+    match stream_id
+        .strip_prefix("user/")
+        .and_then(|rest| rest.split_once('/'))
+    {
+        Some((_user, rest)) => format!("user/-/{}", rest),
+        None => stream_id.to_string(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `subscription/quickadd`
+struct QuickAddResponse {
+    #[serde(rename = "streamId", default)]
+    stream_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `preference/list`
+struct PreferenceList {
+    prefs: Vec<Preference>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response from `unread-count?output=json`
+struct UnreadCountList {
+    unreadcounts: Vec<UnreadCount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnreadCount {
+    id: String,
+    count: usize,
+}
+
+/// Pull the total out of an `unread-count` response, see [GoogleReader::unread_count] for the order things
+/// are tried in.
+fn parse_unread_count(body: &str) -> anyhow::Result<usize> {
+    let counts = serde_json::from_str::<UnreadCountList>(body).ok();
+    if let Some(total) = counts.as_ref().and_then(|counts| {
+        counts.unreadcounts.iter().find(|count| {
+            CategoryRef::parse(&count.id) == CategoryRef::State("reading-list".into())
+        })
+    }) {
+        return Ok(total.count);
+    }
+    if let Ok(total) = body.trim().parse::<usize>() {
+        return Ok(total);
+    }
+    match counts {
+        Some(counts) => Ok(counts
+            .unreadcounts
+            .iter()
+            .filter(|count| count.id.starts_with("feed/"))
+            .map(|count| count.count)
+            .sum()),
+        None => anyhow::bail!("Failed to parse unread count response"),
+    }
+}
+
+/// Parse the server URL, dropping empty path segments so `https://example.com/` and `https://example.com//api/`
+/// don't turn into URLs with `//` in them.
+fn normalize_server_url(server_url: &str) -> anyhow::Result<Url> {
+    let mut url = Url::parse(server_url)?;
+    let segments: Vec<String> = url
+        .path_segments()
+        .with_context(|| format!("{} can't be used as a base URL", server_url))?
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+        .collect();
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("{} can't be used as a base URL", server_url))?
+        .clear()
+        .extend(&segments);
+    Ok(url)
+}
+
+/// The URL for the login endpoint, which is always `accounts/ClientLogin` under the server URL.
+fn login_url(server_url: &Url) -> Url {
+    let mut url = server_url.clone();
+    url.path_segments_mut()
+        .unwrap()
+        .push("accounts")
+        .push("ClientLogin");
+    url
+}
+
+/// The URL for an API endpoint under `api_root` (the server URL and API path), e.g. `&["stream", "contents"]`.
+fn endpoint_url(api_root: &Url, endpoint: &[&str]) -> Url {
+    let mut url = api_root.clone();
+    url.path_segments_mut().unwrap().extend(endpoint);
+    url
+}
+
+/// The URL for a page of `stream/contents/<stream id>`.
+fn stream_contents_url(
+    api_root: &Url,
+    stream_id: &str,
+    continuation: Option<&str>,
+    options: &StreamOptions,
+    timestamp_unit: TimestampUnit,
+) -> Url {
+    // https://your-freshrss-instance-url/api/greader.php/reader/api/0/stream/contents/user/-/state/com.google/reading-list?ot=0&n=1000&r=n&xt=user/-/state/com.google/read
+    let mut url = endpoint_url(api_root, &["stream", "contents"]);
+    // stream IDs are made of path segments, so we can't push the whole thing or the slashes get escaped
+    url.path_segments_mut()
+        .unwrap()
+        .extend(stream_id.split('/'));
+    append_stream_query(&mut url, continuation, options, timestamp_unit);
+    url
+}
+
+/// The URL for the unread items in the reading list.
+fn unread_items_url(
+    api_root: &Url,
+    continuation: Option<&str>,
+    options: &StreamOptions,
+    timestamp_unit: TimestampUnit,
+) -> Url {
+    let options = StreamOptions {
+        exclude: Some(StreamId::READ.to_string()),
+        ..options.clone()
+    };
+    stream_contents_url(
+        api_root,
+        StreamId::READING_LIST,
+        continuation,
+        &options,
+        timestamp_unit,
+    )
+}
+
+/// The URL for a page of `stream/items/ids`, where the stream ID goes in the query.
+fn item_ids_url(
+    api_root: &Url,
+    stream_id: &str,
+    continuation: Option<&str>,
+    options: &StreamOptions,
+    timestamp_unit: TimestampUnit,
+) -> Url {
+    let mut url = endpoint_url(api_root, &["stream", "items", "ids"]);
+    url.query_pairs_mut().append_pair("s", stream_id);
+    append_stream_query(&mut url, continuation, options, timestamp_unit);
+    url
+}
+
+/// Adds the continuation and [StreamOptions] parameters shared by the stream endpoints.
+fn append_stream_query(
+    url: &mut Url,
+    continuation: Option<&str>,
+    options: &StreamOptions,
+    timestamp_unit: TimestampUnit,
+) {
+    /*
+    ot=0: This is the "start time" for the request. Setting it to 0 means that you want to fetch all unread items since the beginning.
+    n=1000: This parameter specifies the maximum number of items to fetch. You can adjust this value to the desired number of items.
+    r=n: This parameter specifies the order in which items are returned. "n" stands for "newest first", "o" for "oldest first".
+    xt=user/-/state/com.google/read: This parameter specifies that you want to exclude items that are already marked as read.
+    */
+    let mut query = url.query_pairs_mut();
+    if let Some(continuation) = continuation {
+        query.append_pair("c", continuation);
+    }
+    if let Some(count) = options.count {
+        query.append_pair("n", &count.to_string());
+    }
+    match options.start_time {
+        Some(start_time) => {
+            query.append_pair("ot", &timestamp_unit.format(start_time));
+        }
+        // FreshRSS has been known to ignore r=o without ot=
+        None if options.order == StreamOrder::OldestFirst => {
+            query.append_pair("ot", "0");
+        }
+        None => {}
+    }
+    if let Some(end_time) = options.end_time {
+        query.append_pair("nt", &timestamp_unit.format(end_time));
+    }
+    query.append_pair("r", options.order.as_param());
+    if let Some(exclude) = &options.exclude {
+        query.append_pair("xt", exclude);
+    }
+    if options.annotations {
+        query.append_pair("annotations", "true");
+    }
+    if options.likes {
+        query.append_pair("likes", "true");
+    }
+    drop(query);
+
+    let sent: HashSet<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+    let mut query = url.query_pairs_mut();
+    for (key, value) in &options.extra_params {
+        if sent.contains(key) {
+            debug!("Skipping extra parameter {}, it's already set", key);
+            continue;
+        }
+        query.append_pair(key, value);
+    }
+}
+
+/// Parse an item ID in either the long or short form, see [Item::short_id].
+fn parse_item_id(item_id: &str) -> Option<u64> {
+    match item_id.strip_prefix("tag:google.com,2005:reader/item/") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => match item_id.parse::<u64>() {
+            Ok(id) => Some(id),
+            Err(_) => item_id.parse::<i64>().ok().map(|id| id as u64),
+        },
+    }
+}
+
+/// Strip the tags out of some HTML, see [Item::plain_text_content].
+fn html_to_text(html: &str) -> String {
+    const BLOCK_TAGS: &[&str] = &[
+        "p",
+        "br",
+        "div",
+        "li",
+        "tr",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "blockquote",
+        "pre",
+        "hr",
+        "ul",
+        "ol",
+        "table",
+    ];
+
+    let mut text = String::new();
+    let mut skip_until: Option<&str> = None;
+    let mut rest = html;
+    while let Some(start) = rest.find(['<', '&']) {
+        if skip_until.is_none() {
+            text.push_str(&rest[..start]);
+        }
+        rest = &rest[start..];
+        if rest.starts_with('&') {
+            let (decoded, used) = decode_entity(rest);
+            if skip_until.is_none() {
+                text.push_str(&decoded);
+            }
+            rest = &rest[used..];
+            continue;
+        }
+
+        // comments can have `>` in them, and only end at `-->`
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = match comment.find("-->") {
+                Some(end) => &comment[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+        // a `<` that doesn't start a tag, like in `a < b`, is just text
+        let starts_tag = rest[1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+        if !starts_tag {
+            if skip_until.is_none() {
+                text.push('<');
+            }
+            rest = &rest[1..];
+            continue;
+        }
+
+        let end = match tag_end(rest) {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = rest[1..end].trim();
+        rest = &rest[end + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match skip_until {
+            Some(skipped) if closing && name == skipped => skip_until = None,
+            Some(_) => {}
+            None if !closing && name == "script" => skip_until = Some("script"),
+            None if !closing && name == "style" => skip_until = Some("style"),
+            None if BLOCK_TAGS.contains(&name.as_str()) => text.push('\n'),
+            None => {}
+        }
+    }
+    if skip_until.is_none() && !rest.starts_with('<') {
+        text.push_str(rest);
+    }
+
+    // collapse the whitespace the markup left behind, keeping at most one blank line between blocks
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Where the tag at the start of `html` ends, skipping any `>` inside quoted attribute values.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decode the HTML entity at the start of `text`, returns what it decodes to and how many bytes it used.
+///
+/// Anything that isn't a known entity is left as a literal `&`.
+fn decode_entity(text: &str) -> (String, usize) {
+    let end = match text[1..].find(';') {
+        Some(end) if end <= 10 => end + 1,
+        _ => return ("&".to_string(), 1),
+    };
+    let entity = &text[1..end];
+    let decoded = match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        "copy" => Some('\u{a9}'),
+        "reg" => Some('\u{ae}'),
+        "trade" => Some('\u{2122}'),
+        "ndash" => Some('\u{2013}'),
+        "mdash" => Some('\u{2014}'),
+        "hellip" => Some('\u{2026}'),
+        "lsquo" => Some('\u{2018}'),
+        "rsquo" => Some('\u{2019}'),
+        "ldquo" => Some('\u{201c}'),
+        "rdquo" => Some('\u{201d}'),
+        _ => match entity.strip_prefix('#') {
+            Some(number) => match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse().ok(),
+            }
+            .and_then(char::from_u32),
+            None => None,
+        },
+    };
+    match decoded {
+        Some(decoded) => (decoded.to_string(), end + 1),
+        None => ("&".to_string(), 1),
+    }
+}
+
+/// Checks the continuation a page came back with, erroring with [Error::PaginationStuck] if it's the one we
+/// just sent rather than looping forever.
+fn next_continuation(current: Option<&str>, next: Option<String>) -> Result<Option<String>, Error> {
+    match next {
+        Some(next) if current == Some(next.as_str()) => {
+            Err(Error::PaginationStuck { continuation: next })
+        }
+        next => Ok(next),
+    }
+}
+
+/// Compare cached item IDs against the current ones, returning `(added, removed)`.
+///
+/// IDs are matched by number where they parse so long and short forms of the same item are equal.
+fn diff_item_ids(cached: &HashSet<String>, current: &[String]) -> (Vec<String>, Vec<String>) {
+    let key = |id: &str| match parse_item_id(id) {
+        Some(number) => number.to_string(),
+        None => id.to_string(),
+    };
+    let cached_keys: HashSet<String> = cached.iter().map(|id| key(id)).collect();
+    let current_keys: HashSet<String> = current.iter().map(|id| key(id)).collect();
+
+    let added = current
+        .iter()
+        .filter(|id| !cached_keys.contains(&key(id)))
+        .cloned()
+        .collect();
+    let mut removed: Vec<String> = cached
+        .iter()
+        .filter(|id| !current_keys.contains(&key(id)))
+        .cloned()
+        .collect();
+    removed.sort();
+    (added, removed)
+}
+
+/// The stream ID for a folder, accepts either a bare name (`Rust`) or a full label ID (`user/-/label/Rust`).
+fn label_stream_id(folder: &str) -> String {
+    match folder.starts_with("user/") {
+        true => folder.to_string(),
+        false => format!("user/-/label/{}", folder),
+    }
+}
+
+/// Convert a chrono time to a [SystemTime], which is `web_time`'s on WASM since the standard library's doesn't
+/// work in the browser.
+#[cfg(feature = "chrono")]
+fn system_time(time: DateTime<Utc>) -> SystemTime {
+    let micros = u64::try_from(time.timestamp_micros()).unwrap_or_default();
+    UNIX_EPOCH + Duration::from_micros(micros)
+}
+
+/// Convert a `time` time to a [SystemTime], like [system_time] does for chrono.
+#[cfg(feature = "time")]
+fn offset_system_time(time: time::OffsetDateTime) -> SystemTime {
+    let micros = u64::try_from(time.unix_timestamp_nanos() / 1000).unwrap_or_default();
+    UNIX_EPOCH + Duration::from_micros(micros)
+}
+
+/// Put items in the order of `item_ids`, dropping the ones that weren't asked for.
+fn order_items(items: Vec<Item>, item_ids: &[String]) -> Vec<Item> {
+    // keyed on the short ID where there is one so the long and short forms match, duplicates queue up
+    let key = |item_id: &str| parse_item_id(item_id).ok_or_else(|| item_id.to_string());
+    let mut by_id: HashMap<Result<u64, String>, VecDeque<Item>> = HashMap::new();
+    for item in items {
+        by_id.entry(key(&item.id)).or_default().push_back(item);
+    }
+    item_ids
+        .iter()
+        .filter_map(|item_id| by_id.get_mut(&key(item_id))?.pop_front())
+        .collect()
+}
+
+/// The HTTP status behind an error from a request that the server refused.
+fn error_status(err: &anyhow::Error) -> Option<reqwest::StatusCode> {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .find_map(reqwest::Error::status)
+}
+
+/// Wait before retrying, using the browser's timers on WASM where tokio's don't run.
+async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// A response body as a stream of chunks, so we can stop reading at `max_response_bytes`.
+#[cfg(not(target_arch = "wasm32"))]
+type BodyStream = futures::stream::BoxStream<'static, reqwest::Result<bytes::Bytes>>;
+/// A response body as a stream of chunks, so we can stop reading at `max_response_bytes`.
+#[cfg(target_arch = "wasm32")]
+type BodyStream = futures::stream::LocalBoxStream<'static, reqwest::Result<bytes::Bytes>>;
+
+fn body_stream(res: reqwest::Response) -> BodyStream {
+    #[cfg(not(target_arch = "wasm32"))]
+    return res.bytes_stream().boxed();
+    #[cfg(target_arch = "wasm32")]
+    return res.bytes_stream().boxed_local();
+}
+
+/// How long a `Retry-After` header asks us to wait, it's either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = parse_http_date(value)?;
+    // a date in the past means we can go again now
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Parse an HTTP date like `Wed, 21 Oct 2015 07:28:00 GMT`, the only format servers should send.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_weekday, day, month, year, time, "GMT"] = parts[..] else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    // days since the epoch for a civil date, from http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146_097 + day_of_era - 719_468).ok()?;
+
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hours * 3600 + minutes * 60 + seconds))
+}
+
+/// Strips any credentials from a URL so it's safe to put in an error or log.
+fn redact_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    // these only fail for URLs that can't have credentials anyway
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    // the write token can be in the query, see GoogleReaderBuilder::write_token_in_query, and so can the
+    // password, see GoogleReaderBuilder::login_method
+    if url
+        .query_pairs()
+        .any(|(key, _)| matches!(key.as_ref(), "T" | "Passwd"))
+    {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| match key.as_ref() {
+                "T" | "Passwd" => (key.into_owned(), REDACTED.to_string()),
+                _ => (key.into_owned(), value.into_owned()),
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url
+}
+
+/// Does all the things.
+impl GoogleReader {
+    /// The server URL is something like `https://example.com/api/greader.php` for FreshRSS
+    pub fn try_new(
+        username: impl ToString,
+        password: impl ToString,
+        server_url: impl ToString,
+    ) -> anyhow::Result<Self> {
+        Self::builder(server_url)
+            .credentials(username, password)
+            .build()
+    }
+
+    /// Start building a client for more control over how it's set up.
+    ///
+    /// The server URL is something like `https://example.com/api/greader.php` for FreshRSS
+    pub fn builder(server_url: impl ToString) -> GoogleReaderBuilder {
+        GoogleReaderBuilder {
+            username: String::new(),
+            password: String::new(),
+            server_url: server_url.to_string(),
+            auth_mode: AuthMode::default(),
+            bearer_token: None,
+            backend: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            compression: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            redirect: None,
+            api_path: "reader/api/0".to_string(),
+            timestamp_unit: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_item_count: DEFAULT_MAX_ITEM_COUNT,
+            rate_limit_retries: 1,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            rate_limit: None,
+            login_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            connect_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            local_address: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            accept_invalid_certs: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            root_certificates: Vec::new(),
+            login_method: LoginMethod::default(),
+            write_token_in_query: false,
+            auto_login: true,
+            default_subscription_folder: None,
+            include_all_direct_stream_ids: true,
+            collect_stats: false,
+            user_agent: None,
+            client: None,
+        }
+    }
+
+    /// Do the login dance and cache the auth token.
+    ///
+    /// If a clone logged in while this was waiting for it to finish, that login's result is returned rather
+    /// than logging in again.
+    pub async fn login(&mut self) -> anyhow::Result<LoginResult> {
+        let before = self.authtoken();
+        let login_lock = self.login_lock.clone();
+        let mut last_login = login_lock.lock().await;
+        if let (Some(last), Some(authtoken)) = (last_login.as_ref(), self.authtoken()) {
+            if Some(&authtoken) != before.as_ref() && last.auth_token == authtoken {
+                return Ok(last.clone());
+            }
+        }
+        let result = self.do_login().await?;
+        *last_login = Some(result.clone());
+        Ok(result)
+    }
+
+    /// Check that the server URL points at a Google Reader API, without logging in.
+    ///
+    /// This asks for a write token without credentials, which a real API refuses with `401`/`403` (or grants,
+    /// if it's already authenticated). Missing pages and web pages are errors, so a typo in the URL shows up
+    /// here rather than as a confusing login failure.
+    pub async fn validate_server(&self) -> anyhow::Result<()> {
+        let url = self.api_url(&["token"]);
+        let res = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to {}", redact_url(&url)))?;
+        let status = res.status();
+        let is_html = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/html"));
+        debug!("validate_server: {} returned {}", redact_url(&url), status);
+
+        let looks_like_api = match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => true,
+            status if status.is_success() => !is_html,
+            _ => false,
+        };
+        if !looks_like_api {
+            anyhow::bail!(
+                "{} doesn't look like a Google Reader API, {} returned {}{}",
+                redact_url(&self.server_url),
+                redact_url(&url),
+                status,
+                if is_html { " with a web page" } else { "" },
+            );
+        }
+        Ok(())
+    }
+
+    /// The request counters, which stay at zero unless the reader was built with
+    /// [GoogleReaderBuilder::collect_stats].
+    pub fn stats(&self) -> &SyncStats {
+        &self.stats
+    }
+
+    /// Reset the request counters and start timing a sync, finish it with [GoogleReader::finish_sync].
+    pub fn start_sync(&mut self) {
+        self.stats.requests_made = 0;
+        self.stats.bytes_received = 0;
+        self.sync_started = Some(Instant::now());
+    }
+
+    /// Stop timing the sync started with [GoogleReader::start_sync], returns the stats for it.
+    pub fn finish_sync(&mut self) -> &SyncStats {
+        if let Some(started) = self.sync_started.take() {
+            self.stats.last_sync_duration = Some(started.elapsed());
+        }
+        &self.stats
+    }
+
+    /// Fetches the subscriptions, tags, unread count and first page of unread items at the same time.
+    ///
+    /// This logs in first if needed, then sends the four requests in parallel from clones of the reader, which
+    /// share its session. Their request counts are added to this reader's [GoogleReader::stats].
+    pub async fn initial_sync(&mut self) -> anyhow::Result<InitialSync> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let mut readers = [self.clone(), self.clone(), self.clone()];
+        for reader in readers.iter_mut() {
+            reader.stats = SyncStats::default();
+        }
+        let [subscriptions_reader, tags_reader, count_reader] = &mut readers;
+        let (subscriptions, tags, unread_count, unread) = futures::join!(
+            subscriptions_reader.list_subscriptions(),
+            tags_reader.list_tags(),
+            count_reader.unread_count(),
+            self.get_unread_items(None),
+        );
+        for reader in &readers {
+            self.stats.requests_made += reader.stats.requests_made;
+            self.stats.bytes_received += reader.stats.bytes_received;
+        }
+
+        Ok(InitialSync {
+            subscriptions: subscriptions.with_context(|| "Failed to list subscriptions")?,
+            tags: tags.with_context(|| "Failed to list tags")?,
+            unread_count: unread_count.with_context(|| "Failed to get the unread count")?,
+            unread: unread.with_context(|| "Failed to get unread items")?,
+        })
+    }
+
+    /// Count response bytes towards [SyncStats::bytes_received].
+    fn record_bytes_received(&mut self, bytes: usize) {
+        if self.collect_stats {
+            self.stats.bytes_received += bytes as u64;
+        }
+    }
+
+    /// The server software we're talking to.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// The current session's tokens, to save for later with [GoogleReader::restore_session].
+    pub fn session(&self) -> Session {
+        Session {
+            auth_token: self.authtoken(),
+            write_token: self.write_token(),
+        }
+    }
+
+    /// Use a previously saved session, this is shared with any clones of this client.
+    pub fn restore_session(&mut self, session: Session) {
+        *self.authtoken.lock().unwrap() = session.auth_token;
+        *self.write_token.lock().unwrap() = session.write_token;
+    }
+
+    /// Forget the session, clearing the cached tokens and zeroizing the password in memory.
+    ///
+    /// None of the GReader-style servers we know of have a logout endpoint, so this is purely local and the
+    /// server-side session is left to expire. Clones of this client share the session and the password, so
+    /// they're logged out too, and logging in again from any of them fails with [Error::LoggedOut].
+    pub fn logout(&mut self) -> anyhow::Result<()> {
+        if let Some(mut authtoken) = self.authtoken.lock().unwrap().take() {
+            authtoken.zeroize();
+        }
+        if let Some(mut write_token) = self.write_token.lock().unwrap().take() {
+            write_token.zeroize();
+        }
+        if let Some(mut password) = self.password.lock().unwrap().take() {
+            password.zeroize();
+        }
+        // if a login is in flight it hasn't got a token to keep yet
+        if let Ok(mut last_login) = self.login_lock.try_lock() {
+            if let Some(mut last_login) = last_login.take() {
+                last_login.auth_token.zeroize();
+            }
+        }
+        Ok(())
+    }
+
+    /// Log in if we don't have an auth token yet.
+    ///
+    /// If another caller is already logging in, this waits for it and uses its token rather than logging in again.
+    async fn ensure_login(&mut self) -> anyhow::Result<()> {
+        if self.authtoken().is_some() {
+            return Ok(());
+        }
+        if !self.auto_login {
+            return Err(Error::NotLoggedIn.into());
+        }
+        let login_lock = self.login_lock.clone();
+        let mut last_login = login_lock.lock().await;
+        // someone else may have finished logging in while we waited for the lock
+        if self.authtoken().is_some() {
+            return Ok(());
+        }
+        *last_login = Some(self.do_login().await?);
+        Ok(())
+    }
+
+    /// Sends the login request, callers should hold `login_lock`.
+    async fn do_login(&mut self) -> anyhow::Result<LoginResult> {
+        if self.auth_mode == AuthMode::Bearer {
+            anyhow::bail!("Can't log in with bearer token authentication, set a new token instead");
+        }
+        let url = login_url(&self.server_url);
+
+        debug!("Login URL: {}", url);
+
+        let mut request = {
+            let password = self.password.lock().unwrap();
+            let password = password.as_ref().ok_or(Error::LoggedOut)?;
+            let params = [("Email", &self.username), ("Passwd", password)];
+            match self.login_method {
+                LoginMethod::PostForm => self.client.post(url).form(&params),
+                LoginMethod::GetQuery => self.client.get(url).query(&params),
+            }
+        };
+        if let Some(login_timeout) = self.login_timeout {
+            request = request.timeout(login_timeout);
+        }
+        let res = self.send("login", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get login response body")?;
+        // the body has the auth token in it, so only say how big it was
+        trace!("Login response: {} bytes", body.len());
+
+        let result =
+            parse_login_response(&body).with_context(|| "Failed to parse login response")?;
+        trace!("Got authtoken ({} characters)", result.auth_token.len());
+        *self.authtoken.lock().unwrap() = Some(result.auth_token.clone());
+
+        Ok(result)
+    }
+
+    /// Get a "write" token.
+    pub async fn get_write_token(&mut self) -> anyhow::Result<String> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+        let url = self.api_url(&["token"]);
+        trace!("get_write_token url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("token", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get write token response body")?;
+        // servers variously send a trailing `\n`, `\r\n` or other whitespace, none of which is part of the token
+        let write_token = body.trim().to_string();
+
+        *self.write_token.lock().unwrap() = Some(write_token.clone());
+
+        Ok(write_token)
+    }
+
+    /// Checks the cached write token still works, by sending an `edit-tag` that doesn't change anything.
+    ///
+    /// Returns `false` if there's no cached token or the server refuses it with a `401`, `403` or the
+    /// `X-Reader-Google-Bad-Token` header, in which case call [GoogleReader::get_write_token] for a new one.
+    /// Other failures are errors, including a `400`, since some servers (like Miniflux) reject an `edit-tag`
+    /// without items whatever the token.
+    pub async fn is_write_token_valid(&mut self) -> anyhow::Result<bool> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+        let Some(write_token) = self.write_token() else {
+            return Ok(false);
+        };
+
+        let url = self.api_url(&["edit-tag"]);
+        let request = self.write_request(url, vec![("T", write_token)])?;
+        const BAD_TOKEN: &str = "X-Reader-Google-Bad-Token";
+        match self.send("edit-tag", request).await {
+            // Google Reader flagged bad tokens with a header rather than the status
+            Ok(res) => Ok(!res.headers().contains_key(BAD_TOKEN)),
+            Err(err) => match error_status(&err) {
+                Some(status) if matches!(status.as_u16(), 401 | 403) => Ok(false),
+                // there was a response, so the saved headers are its
+                Some(_)
+                    if self
+                        .last_response_headers
+                        .as_ref()
+                        .is_some_and(|headers| headers.contains_key(BAD_TOKEN)) =>
+                {
+                    Ok(false)
+                }
+                _ => Err(err),
+            },
+        }
+    }
+
+    /// Returns a list of unread item IDs.
+    pub async fn get_unread_items(
+        &mut self,
+        continuation: Option<String>,
+    ) -> anyhow::Result<Response> {
+        self.get_unread_items_with_options(continuation, StreamOptions::default())
+            .await
+    }
+
+    /// Returns a list of unread item IDs, using the given [StreamOptions].
+    pub async fn get_unread_items_with_options(
+        &mut self,
+        continuation: Option<String>,
+        options: StreamOptions,
+    ) -> anyhow::Result<Response> {
+        let options = StreamOptions {
+            exclude: Some(StreamId::READ.to_string()),
+            ..options
+        };
+        self.get_stream_contents(StreamId::READING_LIST, continuation, options)
+            .await
+    }
+
+    /// Carries on fetching unread items from a continuation you saved earlier, a page at a time.
+    ///
+    /// Continuations are opaque values from the server, the crate doesn't keep any state for them, so they
+    /// work across restarts for as long as the server accepts them. FreshRSS bases them on item IDs so they
+    /// don't expire, others may use offsets (which shift as items are read) or expire them, in which case
+    /// [Paginator::next_page] fails with [Error::ContinuationExpired].
+    pub fn resume_unread_fetch(&mut self, continuation: String) -> Paginator<'_, Item> {
+        Paginator {
+            reader: self,
+            stream_id: StreamId::READING_LIST.to_string(),
+            options: StreamOptions {
+                exclude: Some(StreamId::READ.to_string()),
+                ..Default::default()
+            },
+            continuation: Some(continuation),
+            done: false,
+            _item: PhantomData,
+        }
+    }
+
+    /// Returns a page of the unread items in one feed (or any other stream), e.g. `feed/1`.
+    pub async fn get_feed_unread(
+        &mut self,
+        stream_id: impl ToString,
+        continuation: Option<String>,
+    ) -> anyhow::Result<Response> {
+        let options = StreamOptions {
+            exclude: Some(StreamId::READ.to_string()),
+            ..Default::default()
+        };
+        self.get_stream_contents(stream_id, continuation, options)
+            .await
+    }
+
+    /// Returns the unread items whose IDs aren't in `known_ids`, for polling without re-downloading what you've
+    /// already got.
+    ///
+    /// Items come newest first, so once a page ends with an item we already know about we stop, as everything
+    /// after it is older. If you've only seen some of the older items, they might not all be returned.
+    pub async fn fetch_new_unread(
+        &mut self,
+        known_ids: &HashSet<String>,
+    ) -> anyhow::Result<Vec<Item>> {
+        let mut items = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self.get_unread_items(continuation.clone()).await?;
+            let reached_known = response
+                .items
+                .last()
+                .is_some_and(|item| known_ids.contains(&item.id));
+            items.extend(
+                response
+                    .items
+                    .into_iter()
+                    .filter(|item| !known_ids.contains(&item.id)),
+            );
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) if !reached_known => continuation = Some(next),
+                _ => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Returns the unread items newer than `last_id`, which can be in either the long or short form.
+    ///
+    /// Items come newest first, so this stops as soon as it reaches `last_id`. If `last_id` isn't unread any
+    /// more it won't be found, and you get every unread item.
+    pub async fn get_unread_since_id(
+        &mut self,
+        last_id: impl ToString,
+    ) -> anyhow::Result<Vec<Item>> {
+        let last_id = last_id.to_string();
+        let mut items = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self.get_unread_items(continuation.clone()).await?;
+            if let Some(position) = response.items.iter().position(|item| item.has_id(&last_id)) {
+                items.extend(response.items.into_iter().take(position));
+                break;
+            }
+            items.extend(response.items);
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Returns the unread items as untyped JSON, for when you need fields that [Item] doesn't model yet.
+    pub async fn get_unread_items_raw(
+        &mut self,
+        continuation: Option<String>,
+    ) -> anyhow::Result<serde_json::Value> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let url = unread_items_url(
+            &self.api_root(),
+            continuation.as_deref(),
+            &StreamOptions::default(),
+            self.timestamp_unit,
+        );
+        trace!("url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("stream/contents", request).await?;
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get stream contents response body")?;
+        serde_json::from_str(&body).with_context(|| "Failed to parse stream contents response body")
+    }
+
+    /// Returns the contents of a stream, e.g. `user/-/state/com.google/starred` or `feed/<feed id>`.
+    pub async fn get_stream_contents(
+        &mut self,
+        stream_id: impl ToString,
+        continuation: Option<String>,
+        options: StreamOptions,
+    ) -> anyhow::Result<Response> {
+        let res = self
+            .send_stream_contents(&stream_id.to_string(), continuation.as_deref(), &options)
+            .await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get stream contents response body")?;
+        #[cfg(debug_assertions)]
+        trace!("Response body:\n{}", body);
+        let response: Response = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse stream contents response body")?;
+        debug!("response: {:#?}", response);
+
+        Ok(response)
+    }
+
+    /// Sends a `stream/contents` request, leaving the body for the caller to read.
+    async fn send_stream_contents(
+        &mut self,
+        stream_id: &str,
+        continuation: Option<&str>,
+        options: &StreamOptions,
+    ) -> anyhow::Result<reqwest::Response> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let url = stream_contents_url(
+            &self.api_root(),
+            stream_id,
+            continuation,
+            options,
+            self.timestamp_unit,
+        );
+        trace!("url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        self.send("stream/contents", request).await
+    }
+
+    /// Returns the IDs of the items in a stream without their contents, which is much cheaper than
+    /// [GoogleReader::get_stream_contents] when you only need to know what's there.
+    pub async fn get_item_ids(
+        &mut self,
+        stream_id: impl ToString,
+        continuation: Option<String>,
+        options: StreamOptions,
+    ) -> anyhow::Result<ItemIds> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let url = item_ids_url(
+            &self.api_root(),
+            &stream_id.to_string(),
+            continuation.as_deref(),
+            &options,
+            self.timestamp_unit,
+        );
+        trace!("url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("stream/items/ids", request).await?;
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get item ids response body")?;
+        serde_json::from_str(&body).with_context(|| "Failed to parse item ids response body")
+    }
+
+    /// Returns a page of a stream, either just the item IDs or the full contents depending on `detail`.
+    ///
+    /// Note that [Detail::IdsOnly] returns short (decimal) item IDs where [Detail::Full] returns the long form.
+    pub async fn get_stream(
+        &mut self,
+        stream_id: impl ToString,
+        continuation: Option<String>,
+        options: StreamOptions,
+        detail: Detail,
+    ) -> anyhow::Result<StreamPage> {
+        match detail {
+            Detail::IdsOnly => self
+                .get_item_ids(stream_id, continuation, options)
+                .await
+                .map(StreamPage::Ids),
+            Detail::Full => self
+                .get_stream_contents(stream_id, continuation, options)
+                .await
+                .map(StreamPage::Full),
+        }
+    }
+
+    /// Returns the IDs of read items with a timestamp after `since`, following continuations.
+    ///
+    /// The API doesn't record when an item was marked read, so `since` is compared against the item's own
+    /// timestamp (`ot=`), which means this finds read items that arrived after `since` rather than every item
+    /// read since then. That's usually what you want when reconciling a local cache that was synced at `since`.
+    ///
+    /// See [GoogleReader::items_marked_read_since_time] if you're not using chrono.
+    #[cfg(feature = "chrono")]
+    pub async fn items_marked_read_since(
+        &mut self,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<String>> {
+        self.items_marked_read_since_time(system_time(since)).await
+    }
+
+    /// [GoogleReader::items_marked_read_since] with a [SystemTime].
+    pub async fn items_marked_read_since_time(
+        &mut self,
+        since: SystemTime,
+    ) -> anyhow::Result<Vec<String>> {
+        let options = StreamOptions {
+            start_time: Some(since),
+            ..Default::default()
+        };
+        self.get_all_item_ids(StreamId::READ, options).await
+    }
+
+    /// [GoogleReader::items_marked_read_since] with a `time` time.
+    #[cfg(feature = "time")]
+    pub async fn items_marked_read_since_offset_datetime(
+        &mut self,
+        since: time::OffsetDateTime,
+    ) -> anyhow::Result<Vec<String>> {
+        self.items_marked_read_since_time(offset_system_time(since))
+            .await
+    }
+
+    /// Whether there are unread items newer than `last_newest_timestamp`, fetching just the newest unread item's ID
+    /// and timestamp.
+    ///
+    /// Returns `false` if there's nothing unread. If the server doesn't send timestamps with IDs, any unread item
+    /// counts as new.
+    ///
+    /// See [GoogleReader::has_new_unread_since_time] if you're not using chrono.
+    #[cfg(feature = "chrono")]
+    pub async fn has_new_unread_since(
+        &mut self,
+        last_newest_timestamp: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        self.has_new_unread_since_time(system_time(last_newest_timestamp))
+            .await
+    }
+
+    /// [GoogleReader::has_new_unread_since] with a `time` time.
+    #[cfg(feature = "time")]
+    pub async fn has_new_unread_since_offset_datetime(
+        &mut self,
+        last_newest_timestamp: time::OffsetDateTime,
+    ) -> anyhow::Result<bool> {
+        self.has_new_unread_since_time(offset_system_time(last_newest_timestamp))
+            .await
+    }
+
+    /// [GoogleReader::has_new_unread_since] with a [SystemTime].
+    pub async fn has_new_unread_since_time(
+        &mut self,
+        last_newest_timestamp: SystemTime,
+    ) -> anyhow::Result<bool> {
+        let last_newest_micros = last_newest_timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let options = StreamOptions {
+            count: Some(1),
+            exclude: Some(StreamId::READ.to_string()),
+            ..Default::default()
+        };
+        let response = self
+            .get_item_ids(StreamId::READING_LIST, None, options)
+            .await?;
+        let newest = match response.item_refs.first() {
+            Some(newest) => newest,
+            None => return Ok(false),
+        };
+        Ok(
+            match newest
+                .timestamp_usec
+                .as_deref()
+                .and_then(|timestamp| timestamp.parse::<u128>().ok())
+            {
+                Some(timestamp) => timestamp > last_newest_micros,
+                None => true,
+            },
+        )
+    }
+
+    /// Every item ID in a stream, following continuations.
+    async fn get_all_item_ids(
+        &mut self,
+        stream_id: &str,
+        options: StreamOptions,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self
+                .get_item_ids(stream_id, continuation.clone(), options.clone())
+                .await?;
+            ids.extend(response.item_refs.into_iter().map(|item_ref| item_ref.id));
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Counts every item in a stream, read or not, by paging through `stream/items/ids`.
+    ///
+    /// No server reports a total, so this can take a lot of requests on a big feed. It stops once it gets to
+    /// [GoogleReaderBuilder::max_item_count] and returns that, so a result equal to the cap means "at least".
+    pub async fn feed_item_count(&mut self, stream_id: impl ToString) -> anyhow::Result<usize> {
+        let stream_id = stream_id.to_string();
+        let options = StreamOptions {
+            count: Some(1000),
+            ..Default::default()
+        };
+        let mut count = 0;
+        let mut continuation = None;
+        loop {
+            let response = self
+                .get_item_ids(&stream_id, continuation.clone(), options.clone())
+                .await?;
+            count += response.item_refs.len();
+            if count >= self.max_item_count {
+                debug!(
+                    "Stopped counting {} at {} items",
+                    stream_id, self.max_item_count
+                );
+                return Ok(self.max_item_count);
+            }
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(count)
+    }
+
+    /// Searches for `query` within a stream, e.g. a folder (`user/-/label/Rust`) or a feed, returning the IDs of
+    /// the matching items, following continuations.
+    ///
+    /// The query goes to `search/items/ids` as `q=`, with the stream as the scope (`s=`), so it's up to the
+    /// server how it matches. Returns [Error::NotSupported] for Miniflux, which doesn't search.
+    pub async fn search_in_stream(
+        &mut self,
+        stream_id: impl ToString,
+        query: impl ToString,
+    ) -> anyhow::Result<Vec<String>> {
+        if matches!(self.backend, Backend::Miniflux) {
+            return Err(Error::NotSupported {
+                operation: "Searching a stream",
+                backend: self.backend,
+            }
+            .into());
+        }
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let stream_id = stream_id.to_string();
+        let query = query.to_string();
+        let mut ids = Vec::new();
+        let mut continuation = None;
+        loop {
+            let mut url = self.api_url(&["search", "items", "ids"]);
+            url.query_pairs_mut()
+                .append_pair("q", &query)
+                .append_pair("s", &stream_id);
+            append_stream_query(
+                &mut url,
+                continuation.as_deref(),
+                &StreamOptions::default(),
+                self.timestamp_unit,
+            );
+            trace!("url: {}", url);
+            let request = self.client.get(url).headers(self.get_auth_headers()?);
+            let res = self.send("search/items/ids", request).await?;
+            let body = self
+                .read_body(res)
+                .await
+                .with_context(|| "Failed to get search response body")?;
+            let response: ItemIds = serde_json::from_str(&body)
+                .with_context(|| "Failed to parse search response body")?;
+            ids.extend(response.item_refs.into_iter().map(|item_ref| item_ref.id));
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Works out which items were read, unread, starred or unstarred elsewhere, by fetching the current read
+    /// and starred IDs and comparing them to the sets you've cached locally.
+    ///
+    /// The API doesn't have a log of state changes, so this pulls every ID in both states, which can be a lot
+    /// of (small) requests on a big account. Cached IDs can be in either the long or short form, IDs in the
+    /// result are in whichever form they came from, short for new states and cached for removed ones.
+    pub async fn state_changes_since(
+        &mut self,
+        cached_read: &HashSet<String>,
+        cached_starred: &HashSet<String>,
+    ) -> anyhow::Result<StateDiff> {
+        let read = self
+            .get_all_item_ids(StreamId::READ, StreamOptions::default())
+            .await
+            .with_context(|| "Failed to get read item ids")?;
+        let starred = self
+            .get_all_item_ids(StreamId::STARRED, StreamOptions::default())
+            .await
+            .with_context(|| "Failed to get starred item ids")?;
+
+        let (newly_read, newly_unread) = diff_item_ids(cached_read, &read);
+        let (newly_starred, newly_unstarred) = diff_item_ids(cached_starred, &starred);
+        Ok(StateDiff {
+            newly_read,
+            newly_unread,
+            newly_starred,
+            newly_unstarred,
+        })
+    }
+
+    /// Streams every item in a stream, following continuations, parsing items as the response arrives rather
+    /// than reading whole pages into memory first.
+    pub fn stream_items(
+        &mut self,
+        stream_id: impl ToString,
+        options: StreamOptions,
+    ) -> impl Stream<Item = anyhow::Result<Item>> + '_ {
+        let state = ItemStreamState {
+            reader: self,
+            stream_id: stream_id.to_string(),
+            options,
+            response: None,
+            splitter: ItemSplitter::default(),
+            page_bytes: 0,
+            pending: VecDeque::new(),
+            continuation: None,
+            done: false,
+        };
+        futures::stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.pending.pop_front() {
+                    return Some((Ok(item), state));
+                }
+                if state.done {
+                    return None;
+                }
+                let result = state.next_chunk().await;
+                if let Err(err) = result {
+                    state.done = true;
+                    return Some((Err(err), state));
+                }
+            }
+        })
+    }
+
+    /// Streams every unread item, see [GoogleReader::stream_items].
+    pub fn stream_unread_items(
+        &mut self,
+        options: StreamOptions,
+    ) -> impl Stream<Item = anyhow::Result<Item>> + '_ {
+        let options = StreamOptions {
+            exclude: Some(StreamId::READ.to_string()),
+            ..options
+        };
+        self.stream_items(StreamId::READING_LIST, options)
+    }
+
+    /// Returns the shared items (broadcast) stream, only a few classic Google Reader clones still support this.
+    pub async fn get_broadcast_items(
+        &mut self,
+        continuation: Option<String>,
+    ) -> anyhow::Result<Response> {
+        self.get_stream_contents(StreamId::BROADCAST, continuation, StreamOptions::default())
+            .await
+    }
+
+    /// Returns every item in the reading list, read or not, following continuations.
+    ///
+    /// This can be a lot of data, set [StreamOptions::count] to control the page size.
+    pub async fn get_all_items(&mut self, options: StreamOptions) -> anyhow::Result<Vec<Item>> {
+        let mut items = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self
+                .get_stream_contents(
+                    StreamId::READING_LIST,
+                    continuation.clone(),
+                    options.clone(),
+                )
+                .await?;
+            items.extend(response.items);
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Returns every item in the reading list published within the last `within`, read or not.
+    pub async fn recent_items(
+        &mut self,
+        within: Duration,
+        options: StreamOptions,
+    ) -> anyhow::Result<Vec<Item>> {
+        let since = SystemTime::now().checked_sub(within).unwrap_or(UNIX_EPOCH);
+        let since_secs = since
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as usize;
+        let options = StreamOptions {
+            start_time: Some(since),
+            ..options
+        };
+
+        let mut items = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self
+                .get_stream_contents(
+                    StreamId::READING_LIST,
+                    continuation.clone(),
+                    options.clone(),
+                )
+                .await?;
+            // ot= is the crawl time on some servers, so check the published time too
+            items.extend(response.items.into_iter().filter(|item| {
+                item.published
+                    .is_none_or(|published| published >= since_secs)
+            }));
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Returns every item in a stream published between `from` and `to` (inclusive), following continuations.
+    ///
+    /// The range goes to the server as `ot=` and `nt=`, and since not every server applies those to the
+    /// published time (or at all), the results are filtered again here.
+    ///
+    /// See [GoogleReader::items_in_range_time] if you're not using chrono.
+    #[cfg(feature = "chrono")]
+    pub async fn items_in_range(
+        &mut self,
+        stream_id: impl ToString,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Item>> {
+        self.items_in_range_time(stream_id, system_time(from), system_time(to))
+            .await
+    }
+
+    /// [GoogleReader::items_in_range] with `time` times.
+    #[cfg(feature = "time")]
+    pub async fn items_in_range_offset_datetime(
+        &mut self,
+        stream_id: impl ToString,
+        from: time::OffsetDateTime,
+        to: time::OffsetDateTime,
+    ) -> anyhow::Result<Vec<Item>> {
+        self.items_in_range_time(stream_id, offset_system_time(from), offset_system_time(to))
+            .await
+    }
+
+    /// [GoogleReader::items_in_range] with [SystemTime]s.
+    pub async fn items_in_range_time(
+        &mut self,
+        stream_id: impl ToString,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> anyhow::Result<Vec<Item>> {
+        let stream_id = stream_id.to_string();
+        let options = StreamOptions {
+            start_time: Some(from),
+            end_time: Some(to),
+            ..Default::default()
+        };
+        let seconds = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        };
+        let (from, to) = (seconds(from), seconds(to));
+
+        let mut items = Vec::new();
+        let mut continuation = None;
+        loop {
+            let response = self
+                .get_stream_contents(&stream_id, continuation.clone(), options.clone())
+                .await?;
+            items.extend(response.items.into_iter().filter(|item| {
+                item.published
+                    .is_none_or(|published| (from..=to).contains(&(published as u64)))
+            }));
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+        Ok(items)
+    }
+
+    /// Fetch a single item from `stream/items/contents`.
+    ///
+    /// Item IDs are strings, in either the long or short form, as they don't fit in a `usize` on 32-bit targets.
+    pub async fn get_item(&mut self, item_id: impl ToString) -> anyhow::Result<Item> {
+        let item_id = item_id.to_string();
+        self.get_items(std::slice::from_ref(&item_id))
+            .await?
+            .into_iter()
+            .next()
+            .with_context(|| format!("Item {} wasn't found", item_id))
+    }
+
+    /// Fetch the given items, in the same order as `item_ids` since servers don't keep to it.
+    ///
+    /// IDs can be in either the long or short form, items the server didn't return are left out.
+    pub async fn get_items(&mut self, item_ids: &[String]) -> anyhow::Result<Vec<Item>> {
+        if item_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self.get_items_contents(item_ids).await?;
+        Ok(order_items(response.items, item_ids))
+    }
+
+    /// Returns the notes left on an item.
+    ///
+    /// Returns [Error::NotSupported] for FreshRSS and Miniflux, which don't store annotations.
+    pub async fn get_item_annotations(
+        &mut self,
+        item_id: impl ToString,
+    ) -> anyhow::Result<Vec<Annotation>> {
+        if matches!(self.backend, Backend::FreshRss | Backend::Miniflux) {
+            return Err(Error::NotSupported {
+                operation: "Item annotations",
+                backend: self.backend,
+            }
+            .into());
+        }
+        let item_id = item_id.to_string();
+        let response = self
+            .get_items_contents(std::slice::from_ref(&item_id))
+            .await?;
+        let item = response
+            .items
+            .into_iter()
+            .next()
+            .with_context(|| format!("Item {} wasn't found", item_id))?;
+        Ok(item.annotations)
+    }
+
+    /// Fetch the given items with a `stream/items/contents` request.
+    async fn get_items_contents(&mut self, item_ids: &[String]) -> anyhow::Result<Response> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let params: Vec<(&str, &str)> = item_ids
+            .iter()
+            .map(|item_id| ("i", item_id.as_str()))
+            .collect();
+        let url = self.api_url(&["stream", "items", "contents"]);
+        trace!("url: {}", url);
+        let request = self
+            .client
+            .post(url)
+            .form(&params)
+            .headers(self.get_auth_headers()?);
+        let res = self.send("stream/items/contents", request).await?;
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get item contents response body")?;
+        serde_json::from_str(&body).with_context(|| "Failed to parse item contents response body")
+    }
+
+    /// The URL for an API endpoint, e.g. `&["stream", "contents"]`.
+    fn api_url(&self, endpoint: &[&str]) -> Url {
+        endpoint_url(&self.api_root(), endpoint)
+    }
+
+    /// The server URL with the API path, which the endpoints go under.
+    fn api_root(&self) -> Url {
+        let mut url = self.server_url.clone();
+        url.path_segments_mut().unwrap().extend(&self.api_path);
+        url
+    }
+
+    /// Sends a request, keeping hold of the response headers.
+    ///
+    /// Errors include the operation name and the URL (without any credentials), and non-success statuses are errors.
+    async fn send(
+        &mut self,
+        operation: &str,
+        request: RequestBuilder,
+    ) -> anyhow::Result<reqwest::Response> {
+        let request = request
+            .build()
+            .with_context(|| format!("Failed to build {} request", operation))?;
+        let url = redact_url(request.url());
+        let mut request = request;
+        let mut retries = 0;
+        let res = loop {
+            // streaming bodies can't be cloned, those requests just don't get retried
+            let retry = if retries < self.rate_limit_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            self.wait_for_rate_limit().await;
+            if self.collect_stats {
+                self.stats.requests_made += 1;
+            }
+            let res = self
+                .client
+                .execute(request)
+                .await
+                // the error's URL isn't redacted
+                .map_err(reqwest::Error::without_url)
+                .with_context(|| format!("Failed to send {} request to {}", operation, url))?;
+            self.last_response_headers = Some(res.headers().clone());
+
+            match (res.status(), retry, retry_after(res.headers())) {
+                (reqwest::StatusCode::TOO_MANY_REQUESTS, Some(retry), Some(wait))
+                    if wait <= self.max_retry_after =>
+                {
+                    debug!(
+                        "{} request to {} was rate limited, retrying in {:?}",
+                        operation, url, wait
+                    );
+                    sleep(wait).await;
+                    request = retry;
+                    retries += 1;
+                }
+                _ => break res,
+            }
+        };
+
+        let status = res.status();
+        match res.error_for_status() {
+            Ok(res) => Ok(res),
+            // the error's URL could have the write token in it, ours is redacted
+            Err(err) => Err(err.without_url())
+                .with_context(|| format!("{} request to {} returned {}", operation, url, status)),
+        }
+    }
+
+    /// Wait until [GoogleReaderBuilder::rate_limit] lets us send another request.
+    async fn wait_for_rate_limit(&self) {
+        let Some(rate_limiter) = &self.rate_limiter else {
+            return;
+        };
+        loop {
+            // the lock's dropped before sleeping, so clones can check in the meantime
+            let wait = match rate_limiter.lock().unwrap().try_acquire() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            trace!("Rate limited, waiting {:?}", wait);
+            sleep(wait).await;
+        }
+    }
+
+    /// How many requests can be sent right now before [GoogleReaderBuilder::rate_limit] makes them wait, `None`
+    /// if there's no limit.
+    pub fn rate_limit_remaining(&self) -> Option<usize> {
+        let mut rate_limiter = self.rate_limiter.as_ref()?.lock().unwrap();
+        rate_limiter.refill();
+        Some(rate_limiter.tokens as usize)
+    }
+
+    /// Start a mutating POST request with the given form, which should include the write token (`T`).
+    ///
+    /// With [GoogleReaderBuilder::write_token_in_query] set, the token is moved to the query string.
+    fn write_request(
+        &self,
+        mut url: Url,
+        mut params: Vec<(&str, String)>,
+    ) -> anyhow::Result<RequestBuilder> {
+        if self.write_token_in_query {
+            let mut query = url.query_pairs_mut();
+            params.retain(|(key, value)| match *key {
+                "T" => {
+                    query.append_pair("T", value);
+                    false
+                }
+                _ => true,
+            });
+        }
+        Ok(self
+            .client
+            .post(url)
+            .form(&params)
+            .headers(self.get_auth_headers()?))
+    }
+
+    /// Start a request to any API endpoint, e.g. `&["stream", "items", "ids"]`, with the auth headers already set.
+    ///
+    /// This is an escape hatch for endpoints and parameters the crate doesn't cover yet, you send the request
+    /// yourself. It fails if we haven't logged in, so call [GoogleReader::login] first.
+    pub fn request(&self, method: Method, path: &[&str]) -> anyhow::Result<RequestBuilder> {
+        Ok(self
+            .client
+            .request(method, self.api_url(path))
+            .headers(self.get_auth_headers()?))
+    }
+
+    /// Reads a response body as text, giving up if it's bigger than `max_response_bytes`.
+    async fn read_body(&mut self, res: reqwest::Response) -> anyhow::Result<String> {
+        let body = read_limited(res, self.max_response_bytes).await?;
+        self.record_bytes_received(body.len());
+        String::from_utf8(body).with_context(|| "Response body wasn't valid UTF-8")
+    }
+
+    /// Returns the headers from the last response received from the server, if any.
+    ///
+    /// Useful for checking headers like `X-RateLimit-Remaining` or `Retry-After` before sending more requests.
+    pub fn last_response_headers(&self) -> Option<&HeaderMap> {
+        self.last_response_headers.as_ref()
+    }
+
+    /// The cached auth token, if we've logged in.
+    fn authtoken(&self) -> Option<String> {
+        self.authtoken.lock().unwrap().clone()
+    }
+
+    /// The cached write token, if we've fetched one.
+    fn write_token(&self) -> Option<String> {
+        self.write_token.lock().unwrap().clone()
+    }
+
+    /// Returns the auth headers for use with the API.
+    ///
+    /// Fails if there's no auth token, or it has characters that can't go in a header.
+    fn get_auth_headers(&self) -> anyhow::Result<HeaderMap> {
+        let authtoken = self.authtoken().ok_or(Error::NotLoggedIn)?;
+        let mut headers = HeaderMap::new();
+        let authorization = match self.auth_mode {
+            AuthMode::ClientLogin => format!("GoogleLogin auth={}", authtoken),
+            AuthMode::Bearer => format!("Bearer {}", authtoken),
+        };
+        let mut authorization = reqwest::header::HeaderValue::from_str(&authorization)
+            .with_context(|| "The auth token isn't a valid header value")?;
+        authorization.set_sensitive(true);
+        headers.append("Authorization", authorization);
+        trace!("Auth headers: {:?} Authorization", self.auth_mode);
+        Ok(headers)
+    }
+
+    /// Add and remove any number of states and labels on an item in one request, e.g. label it, mark it read
+    /// and unstar it all at once.
+    ///
+    /// Fails if the server doesn't answer `OK`.
+    pub async fn edit_item_states(
+        &mut self,
+        item_id: impl ToString,
+        add: &[StreamId],
+        remove: &[StreamId],
+    ) -> anyhow::Result<()> {
+        let add: Vec<&str> = add.iter().map(StreamId::as_str).collect();
+        let remove: Vec<&str> = remove.iter().map(StreamId::as_str).collect();
+        self.edit_states(item_id.to_string(), &add, &remove).await?;
+        Ok(())
+    }
+
+    /// What [GoogleReader::edit_item_states] and the single-item helpers share, returns the body so the
+    /// helpers can keep returning it.
+    async fn edit_states(
+        &mut self,
+        item_id: String,
+        add: &[&str],
+        remove: &[&str],
+    ) -> anyhow::Result<String> {
+        let body = self.edit_tag(&[item_id], add, remove).await?;
+        match body.trim() {
+            "OK" => Ok(body),
+            other => anyhow::bail!("edit-tag failed, server responded with {:?}", other),
+        }
+    }
+
+    /// Mark an item as read
+    ///
+    /// `edit-tag` has no parameter for when something happened, so servers record the time the request
+    /// arrives. If you're replaying actions made offline, keep the time they actually happened yourself.
+    ///
+    /// Like [GoogleReader::edit_item_states], this fails if the server doesn't answer `OK`.
+    pub async fn mark_item_read(&mut self, item_id: impl ToString) -> anyhow::Result<String> {
+        self.edit_states(item_id.to_string(), &[StreamId::READ], &[])
+            .await
+    }
+
+    /// Mark an item as unread, undoing [GoogleReader::mark_item_read]
+    pub async fn mark_item_unread(&mut self, item_id: impl ToString) -> anyhow::Result<String> {
+        self.edit_states(item_id.to_string(), &[], &[StreamId::READ])
+            .await
+    }
+
+    /// Archive an item, marking it read and removing the star in a single request.
+    pub async fn archive_item(&mut self, item_id: impl ToString) -> anyhow::Result<String> {
+        self.edit_states(item_id.to_string(), &[StreamId::READ], &[StreamId::STARRED])
+            .await
+    }
+
+    /// Mark everything in a stream as read, returns the response body.
+    ///
+    /// If `timestamp_usec` is set, only items older than it are marked read. Without it, items that arrived
+    /// after you last fetched the stream will be marked read too, so prefer [GoogleReader::mark_all_read_up_to].
+    pub async fn mark_all_read(
+        &mut self,
+        stream_id: impl ToString,
+        timestamp_usec: Option<u64>,
+    ) -> anyhow::Result<String> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let write_token = match self.write_token() {
+            Some(val) => val,
+            None => self
+                .get_write_token()
+                .await
+                .with_context(|| "Failed to get write token")?,
+        };
+
+        let mut params = vec![("s", stream_id.to_string()), ("T", write_token)];
+        if let Some(timestamp_usec) = timestamp_usec {
+            params.push(("ts", timestamp_usec.to_string()));
+        }
+
+        let url = self.api_url(&["mark-all-as-read"]);
+        trace!("mark-all-as-read url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("mark-all-as-read", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get mark-all-as-read response body")?;
+
+        Ok(body)
+    }
+
+    /// Mark everything in a stream as read, up to and including the newest item you've seen.
+    ///
+    /// Items which arrived after `newest_seen` are left alone, so this is safe to call from a UI that's been open a while.
+    pub async fn mark_all_read_up_to(
+        &mut self,
+        stream_id: impl ToString,
+        newest_seen: &Item,
+    ) -> anyhow::Result<String> {
+        let timestamp_usec = newest_seen
+            .timestamp_micros()
+            .with_context(|| format!("Item {} doesn't have a usable timestamp", newest_seen.id))?;
+        self.mark_all_read(stream_id, Some(timestamp_usec)).await
+    }
+
+    /// Mark a lot of items read, using as few requests as possible.
+    pub async fn mark_items_read(&mut self, item_ids: &[String]) -> anyhow::Result<BatchResult> {
+        self.edit_tag_batched(item_ids, &[StreamId::READ], &[])
+            .await
+    }
+
+    /// Mark the items in an already-fetched [Response] which came from `stream_id` as read, returns how
+    /// many were marked.
+    ///
+    /// If any batch fails this is an error, use [GoogleReader::mark_items_read] to find out which items stuck.
+    pub async fn mark_read_by_origin(
+        &mut self,
+        response: &Response,
+        stream_id: &str,
+    ) -> anyhow::Result<usize> {
+        let item_ids: Vec<String> = response
+            .by_origin(stream_id)
+            .into_iter()
+            .map(|item| item.id.clone())
+            .collect();
+        let result = self.mark_items_read(&item_ids).await?;
+        if let Some((item_id, reason)) = result.failed.first() {
+            anyhow::bail!(
+                "Failed to mark {} of {} items read, {}: {}",
+                result.failed.len(),
+                item_ids.len(),
+                item_id,
+                reason
+            );
+        }
+        Ok(result.succeeded.len())
+    }
+
+    /// Mark every read item in a folder as unread again, returns how many were marked.
+    ///
+    /// Servers don't agree on a way to ask for just the read items in a folder, so this fetches the IDs in the
+    /// folder and the IDs that are read and works on the overlap. If any batch fails this is an error.
+    pub async fn mark_folder_unread(&mut self, folder: impl ToString) -> anyhow::Result<usize> {
+        let folder = label_stream_id(&folder.to_string());
+        let in_folder = self
+            .get_all_item_ids(&folder, StreamOptions::default())
+            .await
+            .with_context(|| format!("Failed to get item ids in {}", folder))?;
+        let read: HashSet<u64> = self
+            .get_all_item_ids(StreamId::READ, StreamOptions::default())
+            .await
+            .with_context(|| "Failed to get read item ids")?
+            .iter()
+            .filter_map(|item_id| parse_item_id(item_id))
+            .collect();
+
+        let item_ids: Vec<String> = in_folder
+            .into_iter()
+            .filter(|item_id| parse_item_id(item_id).is_some_and(|id| read.contains(&id)))
+            .collect();
+        let result = self
+            .edit_tag_batched(&item_ids, &[], &[StreamId::READ])
+            .await?;
+        if let Some((item_id, reason)) = result.failed.first() {
+            anyhow::bail!(
+                "Failed to mark {} of {} items unread, {}: {}",
+                result.failed.len(),
+                item_ids.len(),
+                item_id,
+                reason
+            );
+        }
+        Ok(result.succeeded.len())
+    }
+
+    /// Star a lot of items, using as few requests as possible.
+    pub async fn star_items(&mut self, item_ids: &[String]) -> anyhow::Result<BatchResult> {
+        self.edit_tag_batched(item_ids, &[StreamId::STARRED], &[])
+            .await
+    }
+
+    /// Like [GoogleReader::edit_tag], but splits the items into batches of [EDIT_TAG_BATCH_SIZE].
+    ///
+    /// A failed batch doesn't stop the rest, its items end up in [BatchResult::failed]. Only failing to log in
+    /// is an error.
+    async fn edit_tag_batched(
+        &mut self,
+        item_ids: &[String],
+        add: &[&str],
+        remove: &[&str],
+    ) -> anyhow::Result<BatchResult> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let mut result = BatchResult::default();
+        for batch in item_ids.chunks(EDIT_TAG_BATCH_SIZE) {
+            let reason = match self.edit_tag(batch, add, remove).await {
+                Ok(body) if body.trim() == "OK" => None,
+                Ok(body) => Some(format!("Server responded with {:?}", body.trim())),
+                Err(err) => Some(format!("{:#}", err)),
+            };
+            match reason {
+                None => result.succeeded.extend_from_slice(batch),
+                Some(reason) => {
+                    debug!("edit-tag batch of {} items failed: {}", batch.len(), reason);
+                    result.failed.extend(
+                        batch
+                            .iter()
+                            .map(|item_id| (item_id.clone(), reason.clone())),
+                    );
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Add and remove tags on items in a single `edit-tag` request, returns the response body.
+    async fn edit_tag(
+        &mut self,
+        item_ids: &[String],
+        add: &[&str],
+        remove: &[&str],
+    ) -> anyhow::Result<String> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let write_token = match self.write_token() {
+            Some(val) => val,
+            None => self
+                .get_write_token()
+                .await
+                .with_context(|| "Failed to get write token")?,
+        };
+
+        let mut params = vec![("T", write_token)];
+        params.extend(add.iter().map(|tag| ("a", tag.to_string())));
+        params.extend(remove.iter().map(|tag| ("r", tag.to_string())));
+        params.extend(item_ids.iter().map(|item_id| ("i", item_id.to_owned())));
+
+        let url = self.api_url(&["edit-tag"]);
+        trace!("edit-tag url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("edit-tag", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get edit-tag response body")?;
+
+        Ok(body)
+    }
+
+    /// Returns the total number of unread items, however the server reports it.
+    ///
+    /// In order, this uses the reading list's count from the JSON response, then a plain number if that's all
+    /// the server sent, and finally the sum of the per-feed counts.
+    pub async fn unread_count(&mut self) -> anyhow::Result<usize> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let mut url = self.api_url(&["unread-count"]);
+        url.set_query(Some("output=json"));
+        #[cfg(debug_assertions)]
+        trace!("url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("unread-count", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get unread count response body")?;
+
+        parse_unread_count(&body)
+    }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
-/// Item Summary
-pub struct Summary {
-    pub content: Option<String>,
-    pub author: Option<String>,
-}
+    /// Returns the user's server-side preferences as key/value pairs.
+    pub async fn list_preferences(&mut self) -> anyhow::Result<HashMap<String, String>> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
-/// Feed Item
-pub struct Item {
-    pub id: String,
-    #[serde(alias = "crawlTimeMsec")]
-    pub crawl_time_msec: Option<String>,
-    #[serde(alias = "timestampUsec")]
-    pub timestamp_usec: Option<String>,
-    pub updated: Option<usize>,
-    pub published: Option<usize>,
-    pub title: String,
-    pub canonical: Vec<Link>,
-    pub alternate: Vec<Link>,
-    pub categories: Vec<String>,
-    pub origin: HashMap<String, String>,
-    pub summary: Summary,
-}
+        let mut url = self.api_url(&["preference", "list"]);
+        url.set_query(Some("output=json"));
+        trace!("preference/list url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("preference/list", request).await?;
 
-#[derive(Debug, Deserialize)]
-/// Response from the API
-pub struct Response {
-    pub id: String,
-    pub items: Vec<Item>,
-    pub updated: usize,
-    pub continuation: Option<String>,
-}
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get preference list response body")?;
+        let response: PreferenceList = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse preference list response body")?;
 
-/// Does all the things.
-impl GoogleReader {
-    /// The server URL is something like `https://example.com/api/greader.php` for FreshRSS
-    pub fn try_new(
-        username: impl ToString,
-        password: impl ToString,
-        server_url: impl ToString,
-    ) -> anyhow::Result<Self> {
-        let server_url = match server_url.to_string().ends_with('/') {
-            true => server_url
-                .to_string()
-                .strip_suffix('/')
-                .unwrap()
-                .to_string(),
-            false => server_url.to_string(),
+        Ok(response
+            .prefs
+            .into_iter()
+            .map(|pref| (pref.id, pref.value))
+            .collect())
+    }
+
+    /// Set a server-side preference, returns the response body.
+    pub async fn set_preference(
+        &mut self,
+        key: impl ToString,
+        value: impl ToString,
+    ) -> anyhow::Result<String> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let write_token = match self.write_token() {
+            Some(val) => val,
+            None => self
+                .get_write_token()
+                .await
+                .with_context(|| "Failed to get write token")?,
         };
 
-        let server_url = Url::parse(&server_url).with_context(|| "Failed to parse server URL")?;
-        Ok(GoogleReader {
-            username: username.to_string(),
-            password: password.to_string(),
-            server_url,
-            authtoken: None,
-            write_token: None,
-            client: None,
-        })
+        let params = vec![
+            ("k", key.to_string()),
+            ("v", value.to_string()),
+            ("T", write_token),
+        ];
+
+        let url = self.api_url(&["preference", "set"]);
+        trace!("preference/set url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("preference/set", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get preference set response body")?;
+
+        Ok(body)
     }
 
-    /// Do the login dance and cache the auth token.
-    pub async fn login(&mut self) -> anyhow::Result<()> {
-        let mut url = self.server_url.clone();
-        url.path_segments_mut()
-            .unwrap()
-            .push("accounts")
-            .push("ClientLogin");
+    /// Returns one of a stream's preferences, like `ranking` (sort order), or `None` if it isn't set.
+    pub async fn get_stream_preference(
+        &mut self,
+        stream_id: impl ToString,
+        key: impl ToString,
+    ) -> anyhow::Result<Option<String>> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
 
-        debug!("Login URL: {}", url);
+        let mut url = self.api_url(&["preference", "stream", "list"]);
+        url.set_query(Some("output=json"));
+        trace!("preference/stream/list url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("preference/stream/list", request).await?;
 
-        let params = [("Email", &self.username), ("Passwd", &self.password)];
-        if self.client.is_none() {
-            self.client = Some(reqwest::Client::new());
-        }
-        let res = self
-            .client
-            .as_ref()
-            .unwrap()
-            .post(url)
-            .form(&params)
-            .send()
+        let body = self
+            .read_body(res)
             .await
-            .with_context(|| "Failed to send login request")?;
+            .with_context(|| "Failed to get stream preference list response body")?;
+        let response: StreamPreferenceList = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse stream preference list response body")?;
 
-        let auth_parser = regex::Regex::new(r#"Auth=(?P<authtoken>\S+)"#)
-            .with_context(|| "Failed to generate auth parser regex")?;
+        let stream_id = anonymous_stream_id(&stream_id.to_string());
+        let key = key.to_string();
+        Ok(response
+            .streamprefs
+            .into_iter()
+            .filter(|(id, _)| anonymous_stream_id(id) == stream_id)
+            .flat_map(|(_, prefs)| prefs)
+            .find(|pref| pref.id == key)
+            .map(|pref| pref.value))
+    }
 
-        let body = res
-            .text()
+    /// Set one of a stream's preferences, returns the response body.
+    pub async fn set_stream_preference(
+        &mut self,
+        stream_id: impl ToString,
+        key: impl ToString,
+        value: impl ToString,
+    ) -> anyhow::Result<String> {
+        self.ensure_login()
             .await
-            .with_context(|| "Failed to get login response body")?;
-        trace!("Login response: {}", body);
+            .with_context(|| "Failed to login")?;
 
-        let caps = auth_parser
-            .captures(&body)
-            .with_context(|| "Failed to parse login response")?;
-        if let Some(authtoken) = caps.name("authtoken") {
-            trace!("Got authtoken: {}", authtoken.as_str());
-            self.authtoken = Some(authtoken.as_str().to_string());
-        }
+        let write_token = match self.write_token() {
+            Some(val) => val,
+            None => self
+                .get_write_token()
+                .await
+                .with_context(|| "Failed to get write token")?,
+        };
 
-        Ok(())
+        let params = vec![
+            ("s", stream_id.to_string()),
+            ("k", key.to_string()),
+            ("v", value.to_string()),
+            ("T", write_token),
+        ];
+
+        let url = self.api_url(&["preference", "stream", "set"]);
+        trace!("preference/stream/set url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("preference/stream/set", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get stream preference set response body")?;
+
+        Ok(body)
     }
 
-    /// Get a "write" token.
-    pub async fn get_write_token(&mut self) -> anyhow::Result<String> {
-        if self.authtoken.is_none() {
-            self.login().await.with_context(|| "Failed to login")?;
+    /// Ask the server to fetch a feed now, rather than waiting for its next scheduled update.
+    ///
+    /// The GReader API has no refresh endpoint, so this only works where the backend has its own:
+    ///
+    /// | Backend        | Supported |
+    /// | ---            | ---       |
+    /// | Miniflux       | Yes, via its REST API (`PUT /v1/feeds/<id>/refresh`) |
+    /// | FreshRSS       | No        |
+    /// | Inoreader      | No        |
+    /// | The Old Reader | No        |
+    ///
+    /// Miniflux's REST API is authenticated with your Miniflux login, so this only works if your
+    /// Google Reader integration uses the same username and password. Miniflux isn't detected from the URL,
+    /// set it with [GoogleReaderBuilder::backend].
+    ///
+    /// Returns [Error::NotSupported] for the other backends.
+    pub async fn refresh_subscription(&mut self, stream_id: impl ToString) -> anyhow::Result<()> {
+        if self.backend != Backend::Miniflux {
+            return Err(Error::NotSupported {
+                operation: "Refreshing a subscription",
+                backend: self.backend,
+            }
+            .into());
         }
+
+        let stream_id = stream_id.to_string();
+        let feed_id = stream_id
+            .strip_prefix("feed/")
+            .and_then(|feed_id| feed_id.parse::<u64>().ok())
+            .with_context(|| format!("Failed to get Miniflux feed ID from {}", stream_id))?;
+
         let mut url = self.server_url.clone();
         url.path_segments_mut()
             .unwrap()
-            .push("reader")
-            .push("api")
-            .push("0")
-            .push("token");
-        trace!("get_write_token url: {}", url);
-        let res = self
-            .client
-            .as_ref()
-            .unwrap()
-            .get(url)
-            .headers(self.get_auth_headers())
-            .send()
+            .push("v1")
+            .push("feeds")
+            .push(&feed_id.to_string())
+            .push("refresh");
+        trace!("refresh url: {}", url);
+        let request = {
+            let password = self.password.lock().unwrap();
+            let password = password.as_ref().ok_or(Error::LoggedOut)?;
+            self.client
+                .put(url)
+                .basic_auth(&self.username, Some(password))
+        };
+        self.send("refresh", request).await?;
+
+        Ok(())
+    }
+
+    /// Returns the user's tags, which includes folders and the built-in states.
+    pub async fn list_tags(&mut self) -> anyhow::Result<Vec<Tag>> {
+        self.ensure_login()
             .await
-            .with_context(|| "Failed to get write token")?;
+            .with_context(|| "Failed to login")?;
 
-        let mut body = res
-            .text()
+        let mut url = self.api_url(&["tag", "list"]);
+        url.set_query(Some("output=json"));
+        trace!("tag/list url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = self.send("tag/list", request).await?;
+
+        let body = self
+            .read_body(res)
             .await
-            .with_context(|| "Failed to get write token response body")?;
+            .with_context(|| "Failed to get tag list response body")?;
+        let response: TagList = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse tag list response body")?;
+        Ok(response.tags)
+    }
 
-        if body.ends_with('\n') {
-            body = body.strip_suffix('\n').unwrap().to_string();
+    /// Returns the people the user follows or is followed by, from `friend/list`.
+    ///
+    /// Returns [Error::NotSupported] for FreshRSS and Miniflux, or if the server hasn't got the endpoint.
+    pub async fn list_friends(&mut self) -> anyhow::Result<Vec<Friend>> {
+        let not_supported = Error::NotSupported {
+            operation: "Listing friends",
+            backend: self.backend,
+        };
+        if matches!(self.backend, Backend::FreshRss | Backend::Miniflux) {
+            return Err(not_supported.into());
         }
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
 
-        self.write_token = Some(body.to_owned());
+        let mut url = self.api_url(&["friend", "list"]);
+        url.set_query(Some("output=json"));
+        trace!("friend/list url: {}", url);
+        let request = self.client.get(url).headers(self.get_auth_headers()?);
+        let res = match self.send("friend/list", request).await {
+            Ok(res) => res,
+            Err(err) => {
+                return match error_status(&err) {
+                    Some(status) if matches!(status.as_u16(), 404 | 501) => {
+                        Err(not_supported.into())
+                    }
+                    _ => Err(err),
+                }
+            }
+        };
 
-        Ok(body)
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get friend list response body")?;
+        let response: FriendList = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse friend list response body")?;
+        Ok(response.friends)
     }
 
-    /// Returns a list of unread item IDs.
-    pub async fn get_unread_items(
-        &mut self,
-        continuation: Option<String>,
-    ) -> anyhow::Result<Response> {
-        if self.authtoken.is_none() {
-            self.login().await.with_context(|| "Failed to login")?;
+    /// Returns the user's tags in the order they arranged them, from the root stream's `subscription-ordering`
+    /// preference, falling back to the tags' sort IDs.
+    pub async fn list_tags_sorted(&mut self) -> anyhow::Result<Vec<Tag>> {
+        let mut tags = self.list_tags().await?;
+        let ordering = self
+            .get_stream_preference("user/-/state/com.google/root", "subscription-ordering")
+            .await
+            .with_context(|| "Failed to get the tag ordering")?;
+        sort_tags(&mut tags, ordering.as_deref());
+        Ok(tags)
+    }
+
+    /// Returns all the feeds the user is subscribed to.
+    ///
+    /// Most servers send them all at once, but if the server pages the list this follows the continuations until it has them all.
+    pub async fn list_subscriptions(&mut self) -> anyhow::Result<Vec<Subscription>> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
+
+        let mut subscriptions = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            let mut url = self.api_url(&["subscription", "list"]);
+            {
+                let mut query = url.query_pairs_mut();
+                query.append_pair("output", "json");
+                if self.include_all_direct_stream_ids {
+                    query.append_pair("includeAllDirectStreamIds", "true");
+                }
+                if let Some(continuation) = &continuation {
+                    query.append_pair("c", continuation);
+                }
+            }
+            trace!("subscription/list url: {}", url);
+            let request = self.client.get(url).headers(self.get_auth_headers()?);
+            let res = self.send("subscription/list", request).await?;
+
+            let body = self
+                .read_body(res)
+                .await
+                .with_context(|| "Failed to get subscription list response body")?;
+            let response: SubscriptionList = serde_json::from_str(&body)
+                .with_context(|| "Failed to parse subscription list response body")?;
+
+            subscriptions.extend(response.subscriptions);
+            match next_continuation(continuation.as_deref(), response.continuation)? {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
         }
 
-        // https://your-freshrss-instance-url/api/greader.php/reader/api/0/stream/contents/user/-/state/com.google/reading-list?ot=0&n=1000&r=n&xt=user/-/state/com.google/read
+        Ok(subscriptions)
+    }
 
-        let mut url = self.server_url.clone();
-        url.path_segments_mut()
-            .unwrap()
-            .push("reader")
-            .push("api")
-            .push("0")
-            .push("stream")
-            .push("contents")
-            .push("user")
-            .push("-")
-            .push("state")
-            .push("com.google")
-            .push("reading-list");
-        /*
-        ot=0: This is the "start time" for the request. Setting it to 0 means that you want to fetch all unread items since the beginning.
-        n=1000: This parameter specifies the maximum number of items to fetch. You can adjust this value to the desired number of items.
-        r=n: This parameter specifies the order in which items are returned. "n" stands for "newest first."
-        xt=user/-/state/com.google/read: This parameter specifies that you want to exclude items that are already marked as read.
-        */
-        url.set_query(Some("r=n&xt=user/-/state/com.google/read"));
-        if let Some(continuation) = continuation {
-            url.set_query(Some(
-                format!("c={}&{}", continuation, url.query().unwrap_or("")).as_str(),
-            ))
-        };
-        trace!("url: {}", url);
-        let res = self
-            .client
-            .as_ref()
-            .unwrap()
-            .get(url)
-            .headers(self.get_auth_headers())
-            .send()
+    /// Lists subscriptions along with the names of the folders each one's in, see [Subscription::folders].
+    pub async fn subscriptions_with_folders(
+        &mut self,
+    ) -> anyhow::Result<Vec<(Subscription, Vec<String>)>> {
+        Ok(self
+            .list_subscriptions()
+            .await?
+            .into_iter()
+            .map(|subscription| {
+                let folders = subscription.folders();
+                (subscription, folders)
+            })
+            .collect())
+    }
+
+    /// The subscriptions grouped into their folders, for showing as a tree.
+    ///
+    /// Folders come in the order `tag/list` gives them, including empty ones, and a feed in several folders
+    /// shows up in each. Feeds in no folder are in a last node with an empty name.
+    pub async fn subscription_tree(&mut self) -> anyhow::Result<Vec<FolderNode>> {
+        let subscriptions = self
+            .list_subscriptions()
             .await
-            .with_context(|| "Failed to send unread-items request")?;
+            .with_context(|| "Failed to list subscriptions")?;
+        let tags = self
+            .list_tags()
+            .await
+            .with_context(|| "Failed to list tags")?;
+        Ok(folder_tree(subscriptions, &tags))
+    }
 
-        let body = res
-            .text()
+    /// Check a feed URL before subscribing to it.
+    ///
+    /// `subscription/quickadd` always subscribes, so instead this asks for a preview of the feed's stream
+    /// (`stream/contents/feed/<url>`), which servers that support it can answer without you being subscribed.
+    /// Servers which only know about feeds you're subscribed to (like FreshRSS) will report `is_feed: false` for
+    /// anything new.
+    pub async fn probe_feed(&mut self, feed_url: impl ToString) -> anyhow::Result<FeedProbe> {
+        let feed_url = feed_url.to_string();
+        let stream_id = format!("feed/{}", feed_url);
+
+        let subscription = self
+            .list_subscriptions()
             .await
-            .with_context(|| "Failed to parse unread items response body")?;
-        #[cfg(debug_assertions)]
-        trace!("Response body:\n{}", body);
-        let response: Response = serde_json::from_str(&body)
-            .with_context(|| "Failed to parse unread items response body")?;
-        debug!("response: {:#?}", response);
+            .with_context(|| "Failed to list subscriptions")?
+            .into_iter()
+            .find(|subscription| {
+                subscription.id == stream_id || subscription.url.as_deref() == Some(&feed_url)
+            });
 
-        Ok(response)
-    }
+        let preview = self
+            .get_stream_contents(
+                &stream_id,
+                None,
+                StreamOptions {
+                    count: Some(1),
+                    ..Default::default()
+                },
+            )
+            .await;
+        if let Err(err) = &preview {
+            debug!("Couldn't preview {}: {:?}", stream_id, err);
+        }
 
-    pub async fn get_item(&self, _item_id: usize) {}
+        let title = match (&preview, &subscription) {
+            (Ok(preview), _) if preview.title.is_some() => preview.title.clone(),
+            (_, Some(subscription)) => Some(subscription.title.clone()),
+            _ => None,
+        };
+        Ok(FeedProbe {
+            stream_id: subscription
+                .as_ref()
+                .map(|subscription| subscription.id.clone())
+                .unwrap_or(stream_id),
+            is_feed: preview.is_ok() || subscription.is_some(),
+            title,
+            already_subscribed: subscription.is_some(),
+        })
+    }
 
-    /// Returns the auth headers for use with the API.
-    fn get_auth_headers(&self) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        headers.append(
-            "Authorization",
-            format!("GoogleLogin auth={}", self.authtoken.clone().unwrap())
-                .parse()
-                .unwrap(),
-        );
-        #[cfg(debug_assertions)]
-        trace!("Auth headers: {:?}", headers);
-        headers
+    /// Move a subscription into a folder.
+    ///
+    /// With `from` set it's removed from that folder in the same request, otherwise it's just added to `to`.
+    /// Folders can be names (`Rust`) or label IDs (`user/-/label/Rust`).
+    pub async fn move_subscription(
+        &mut self,
+        stream_id: impl ToString,
+        from: Option<String>,
+        to: String,
+    ) -> anyhow::Result<()> {
+        let mut params = vec![
+            ("ac", "edit".to_string()),
+            ("s", stream_id.to_string()),
+            ("a", label_stream_id(&to)),
+        ];
+        if let Some(from) = from {
+            params.push(("r", label_stream_id(&from)));
+        }
+        self.subscription_edit(params).await?;
+        Ok(())
     }
 
-    /// Mark an item as read
-    pub async fn mark_item_read(&mut self, item_id: impl ToString) -> anyhow::Result<String> {
-        if self.authtoken.is_none() {
-            self.login().await.with_context(|| "Failed to login")?;
+    /// Subscribe to a feed with `subscription/quickadd`, returns the new subscription's stream ID.
+    ///
+    /// If there's a [GoogleReaderBuilder::default_subscription_folder] the feed's moved into it afterwards.
+    pub async fn add_subscription(&mut self, feed_url: impl ToString) -> anyhow::Result<String> {
+        let stream_id = self.quickadd(feed_url.to_string()).await?;
+        if let Some(folder) = self.default_subscription_folder.clone() {
+            self.move_subscription(&stream_id, None, folder)
+                .await
+                .with_context(|| format!("Subscribed to {} but failed to move it", stream_id))?;
         }
+        Ok(stream_id)
+    }
+
+    /// Sends a `subscription/quickadd` request, returns the new subscription's stream ID.
+    async fn quickadd(&mut self, feed_url: String) -> anyhow::Result<String> {
+        self.ensure_login()
+            .await
+            .with_context(|| "Failed to login")?;
 
-        let write_token = match &self.write_token {
-            Some(val) => val.to_owned(),
+        let write_token = match self.write_token() {
+            Some(val) => val,
             None => self
                 .get_write_token()
                 .await
                 .with_context(|| "Failed to get write token")?,
         };
+        let params = vec![("quickadd", feed_url.clone()), ("T", write_token)];
 
-        let params = [
-            ("a", "user/-/state/com.google/read"),
-            ("T", &write_token),
-            ("i", &item_id.to_string()),
-        ];
+        let url = self.api_url(&["subscription", "quickadd"]);
+        trace!("subscription/quickadd url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("subscription/quickadd", request).await?;
 
-        let mut url = self.server_url.clone();
-        url.path_segments_mut()
-            .unwrap()
-            .push("reader")
-            .push("api")
-            .push("0")
-            .push("edit-tag");
-        trace!("edit-tag url: {}", url);
-        let res = self
-            .client
-            .as_ref()
-            .unwrap()
-            .post(url)
-            .form(&params)
-            .headers(self.get_auth_headers())
-            .send()
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get quickadd response body")?;
+        let response: QuickAddResponse = serde_json::from_str(&body)
+            .with_context(|| "Failed to parse quickadd response body")?;
+        response
+            .stream_id
+            .with_context(|| format!("The server didn't find a feed at {}", feed_url))
+    }
+
+    /// Subscribe to a feed and put it in a folder, returns the new subscription's stream ID.
+    ///
+    /// The folder can be a name (`Rust`) or a label ID (`user/-/label/Rust`), it's used instead of any
+    /// [GoogleReaderBuilder::default_subscription_folder].
+    pub async fn subscribe_to_folder(
+        &mut self,
+        feed_url: impl ToString,
+        folder: impl ToString,
+    ) -> anyhow::Result<String> {
+        let stream_id = self.quickadd(feed_url.to_string()).await?;
+        self.move_subscription(&stream_id, None, folder.to_string())
             .await
-            .with_context(|| "Failed to get write token")?;
+            .with_context(|| format!("Subscribed to {} but failed to move it", stream_id))?;
+        Ok(stream_id)
+    }
 
-        let body = res
-            .text()
+    /// Sends a `subscription/edit` request with the given parameters, returns the response body.
+    async fn subscription_edit(&mut self, params: Vec<(&str, String)>) -> anyhow::Result<String> {
+        self.ensure_login()
             .await
-            .with_context(|| "Failed to get write token response body")?;
+            .with_context(|| "Failed to login")?;
+
+        let write_token = match self.write_token() {
+            Some(val) => val,
+            None => self
+                .get_write_token()
+                .await
+                .with_context(|| "Failed to get write token")?,
+        };
+        let mut params = params;
+        params.push(("T", write_token));
+
+        let url = self.api_url(&["subscription", "edit"]);
+        trace!("subscription/edit url: {}", url);
+        let request = self.write_request(url, params)?;
+        let res = self.send("subscription/edit", request).await?;
+
+        let body = self
+            .read_body(res)
+            .await
+            .with_context(|| "Failed to get subscription edit response body")?;
 
         Ok(body)
     }
 
-    /// Returns the number of unread items, does'nt work for FreshRSS.
-    pub async fn unread_count(&mut self) -> anyhow::Result<usize> {
-        if self.authtoken.is_none() {
-            self.login().await.with_context(|| "Failed to login")?;
-        }
+    /// Download the icon for a subscription, see [Subscription::favicon_url] for where it comes from.
+    pub async fn fetch_favicon(&mut self, subscription: &Subscription) -> anyhow::Result<Vec<u8>> {
+        let url = subscription
+            .favicon_url()
+            .with_context(|| format!("No favicon URL for {}", subscription.id))?;
+        download_favicon(self.client.clone(), url, self.max_response_bytes).await
+    }
 
-        let mut url = self.server_url.clone();
-        url.path_segments_mut()
-            .unwrap()
-            .push("reader")
-            .push("api")
-            .push("0")
-            .push("unread-count");
-        #[cfg(debug_assertions)]
-        trace!("url: {}", url);
+    /// Stream a download from any URL, like an item's enclosure, without the API auth headers since media
+    /// usually isn't on the API server.
+    ///
+    /// This isn't held to `max_response_bytes`, it's up to the caller how much to read.
+    pub async fn download_media(
+        &self,
+        url: &str,
+    ) -> anyhow::Result<impl Stream<Item = reqwest::Result<bytes::Bytes>>> {
+        let url = Url::parse(url).with_context(|| "Failed to parse media URL")?;
+        trace!("media url: {}", redact_url(&url));
         let res = self
             .client
-            .as_ref()
-            .unwrap()
-            .get(url)
-            .headers(self.get_auth_headers())
+            .get(url.clone())
             .send()
             .await
-            .with_context(|| "Failed to send unread-items request")?;
+            .with_context(|| format!("Failed to send media request to {}", redact_url(&url)))?
+            .error_for_status()
+            .with_context(|| format!("Failed to get media from {}", redact_url(&url)))?;
+        Ok(body_stream(res))
+    }
 
-        let body = res
-            .text()
+    /// Download the icons for every subscription, with up to `concurrency` downloads at once.
+    ///
+    /// Returns them keyed by stream ID, feeds whose icon can't be downloaded are left out rather than failing
+    /// the whole lot.
+    pub async fn fetch_all_favicons(
+        &mut self,
+        concurrency: usize,
+    ) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+        let subscriptions = self
+            .list_subscriptions()
             .await
-            .with_context(|| "Failed to get unread count response body")?;
+            .with_context(|| "Failed to list subscriptions")?;
+
+        let downloads = subscriptions.into_iter().filter_map(|subscription| {
+            let url = subscription.favicon_url()?;
+            let client = self.client.clone();
+            let limit = self.max_response_bytes;
+            Some(async move {
+                let result = download_favicon(client, url, limit).await;
+                (subscription.id, result)
+            })
+        });
+
+        let favicons = futures::stream::iter(downloads)
+            .buffer_unordered(concurrency.max(1))
+            .filter_map(|(stream_id, result)| async move {
+                match result {
+                    Ok(favicon) => Some((stream_id, favicon)),
+                    Err(err) => {
+                        debug!("Skipping favicon for {}: {:?}", stream_id, err);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await;
+        Ok(favicons)
+    }
+}
+
+/// Sealed in a private module, so it can be a bound on [GoogleReaderApi] without being part of the API.
+mod sealed {
+    /// `Send` everywhere except WASM, where the HTTP client's futures can't move between threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub trait MaybeSend: Send {}
+    #[cfg(not(target_arch = "wasm32"))]
+    impl<T: Send> MaybeSend for T {}
+    /// `Send` everywhere except WASM, where the HTTP client's futures can't move between threads.
+    #[cfg(target_arch = "wasm32")]
+    pub trait MaybeSend {}
+    #[cfg(target_arch = "wasm32")]
+    impl<T> MaybeSend for T {}
+}
+use sealed::MaybeSend;
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+/// The core of the API as a trait, so you can depend on `impl GoogleReaderApi` (or `dyn GoogleReaderApi`) in your
+/// code and swap in a fake in your tests.
+pub trait GoogleReaderApi: MaybeSend {
+    /// See [GoogleReader::login]
+    async fn login(&mut self) -> anyhow::Result<LoginResult>;
+    /// See [GoogleReader::get_write_token]
+    async fn get_write_token(&mut self) -> anyhow::Result<String>;
+    /// See [GoogleReader::get_unread_items]
+    async fn get_unread_items(&mut self, continuation: Option<String>) -> anyhow::Result<Response>;
+    /// See [GoogleReader::get_stream_contents]
+    async fn get_stream_contents(
+        &mut self,
+        stream_id: &str,
+        continuation: Option<String>,
+        options: StreamOptions,
+    ) -> anyhow::Result<Response>;
+    /// See [GoogleReader::get_item]
+    async fn get_item(&mut self, item_id: &str) -> anyhow::Result<Item>;
+    /// See [GoogleReader::mark_item_read]
+    async fn mark_item_read(&mut self, item_id: &str) -> anyhow::Result<String>;
+    /// See [GoogleReader::mark_item_unread]
+    async fn mark_item_unread(&mut self, item_id: &str) -> anyhow::Result<String>;
+    /// See [GoogleReader::archive_item]
+    async fn archive_item(&mut self, item_id: &str) -> anyhow::Result<String>;
+    /// See [GoogleReader::mark_all_read]
+    async fn mark_all_read(
+        &mut self,
+        stream_id: &str,
+        timestamp_usec: Option<u64>,
+    ) -> anyhow::Result<String>;
+    /// See [GoogleReader::unread_count]
+    async fn unread_count(&mut self) -> anyhow::Result<usize>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl GoogleReaderApi for GoogleReader {
+    async fn login(&mut self) -> anyhow::Result<LoginResult> {
+        GoogleReader::login(self).await
+    }
+
+    async fn get_write_token(&mut self) -> anyhow::Result<String> {
+        GoogleReader::get_write_token(self).await
+    }
 
-        let response_usize = body
-            .parse::<usize>()
-            .with_context(|| "Failed to parse unread count response")?;
-        Ok(response_usize)
+    async fn get_unread_items(&mut self, continuation: Option<String>) -> anyhow::Result<Response> {
+        GoogleReader::get_unread_items(self, continuation).await
+    }
+
+    async fn get_stream_contents(
+        &mut self,
+        stream_id: &str,
+        continuation: Option<String>,
+        options: StreamOptions,
+    ) -> anyhow::Result<Response> {
+        GoogleReader::get_stream_contents(self, stream_id, continuation, options).await
+    }
+
+    async fn get_item(&mut self, item_id: &str) -> anyhow::Result<Item> {
+        GoogleReader::get_item(self, item_id).await
+    }
+
+    async fn mark_item_read(&mut self, item_id: &str) -> anyhow::Result<String> {
+        GoogleReader::mark_item_read(self, item_id).await
+    }
+
+    async fn mark_item_unread(&mut self, item_id: &str) -> anyhow::Result<String> {
+        GoogleReader::mark_item_unread(self, item_id).await
+    }
+
+    async fn archive_item(&mut self, item_id: &str) -> anyhow::Result<String> {
+        GoogleReader::archive_item(self, item_id).await
+    }
+
+    async fn mark_all_read(
+        &mut self,
+        stream_id: &str,
+        timestamp_usec: Option<u64>,
+    ) -> anyhow::Result<String> {
+        GoogleReader::mark_all_read(self, stream_id, timestamp_usec).await
+    }
+
+    async fn unread_count(&mut self) -> anyhow::Result<usize> {
+        GoogleReader::unread_count(self).await
+    }
+}
+
+/// Extra combinators for streams of items, like those from [GoogleReader::stream_unread_items].
+pub trait ItemStreamExt: Stream<Item = anyhow::Result<Item>> + Sized {
+    /// Mark each item read once it's been consumed, which is when the next one is asked for.
+    ///
+    /// Marks are sent in batches of `batch_size`, and whatever's left is sent when the stream ends. Because the item
+    /// stream borrows its client, pass in a clone for marking, which shares the session.
+    ///
+    /// There are two things to watch for:
+    ///
+    /// - If you drop the stream before it ends, items in the unsent batch stay unread, since marking them takes a
+    ///   request and there's nothing to run it on from `Drop`. Poll it to the end, or use a `batch_size` of 1.
+    /// - Marking items read while paging through an unread stream (`xt=user/-/state/com.google/read`) can make
+    ///   servers that page by offset skip items, as the ones before the continuation drop out of the stream. Use
+    ///   it with streams that aren't filtered on read state, or fetch the IDs first.
+    ///
+    ///
+    /// ```no_run
+    /// # async fn example(mut reader: google_reader::GoogleReader) -> anyhow::Result<()> {
+    /// use futures::StreamExt;
+    /// use google_reader::ItemStreamExt;
+    ///
+    /// let marker = reader.clone();
+    /// let mut items = Box::pin(
+    ///     reader
+    ///         .stream_unread_items(Default::default())
+    ///         .mark_read_on_yield(marker, 50),
+    /// );
+    /// while let Some(item) = items.next().await {
+    ///     println!("{}", item?.title);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn mark_read_on_yield(
+        self,
+        reader: GoogleReader,
+        batch_size: usize,
+    ) -> impl Stream<Item = anyhow::Result<Item>> {
+        let state = MarkReadState {
+            items: Box::pin(self),
+            reader,
+            batch_size: batch_size.max(1),
+            batch: Vec::new(),
+            last_yielded: None,
+            finished: false,
+        };
+        futures::stream::unfold(state, |mut state| async move {
+            // the items stream can't be polled again once it's ended
+            if state.finished {
+                return None;
+            }
+            if let Some(item_id) = state.last_yielded.take() {
+                state.batch.push(item_id);
+            }
+            if state.batch.len() >= state.batch_size {
+                if let Err(err) = state.flush().await {
+                    return Some((Err(err), state));
+                }
+            }
+            match state.items.next().await {
+                Some(Ok(item)) => {
+                    state.last_yielded = Some(item.id.clone());
+                    Some((Ok(item), state))
+                }
+                Some(Err(err)) => Some((Err(err), state)),
+                None => {
+                    state.finished = true;
+                    match state.flush().await {
+                        Ok(()) => None,
+                        Err(err) => Some((Err(err), state)),
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl<S: Stream<Item = anyhow::Result<Item>>> ItemStreamExt for S {}
+
+/// The state behind [ItemStreamExt::mark_read_on_yield]
+struct MarkReadState<S> {
+    items: std::pin::Pin<Box<S>>,
+    reader: GoogleReader,
+    batch_size: usize,
+    batch: Vec<String>,
+    last_yielded: Option<String>,
+    /// The items have run out, so there's nothing left after the last flush
+    finished: bool,
+}
+
+impl<S> MarkReadState<S> {
+    /// Mark the batch read, it's cleared even if this fails so we don't retry the same batch forever.
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.batch);
+        let body = self
+            .reader
+            .edit_tag(&batch, &[StreamId::READ], &[])
+            .await
+            .with_context(|| format!("Failed to mark {} items read", batch.len()))?;
+        match body.trim() {
+            "OK" => Ok(()),
+            other => anyhow::bail!(
+                "Failed to mark {} items read, server responded with {:?}",
+                batch.len(),
+                other
+            ),
+        }
     }
 }